@@ -1,11 +1,312 @@
 use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_hanabi::ParticleEffect;
+use bevy_rapier2d::prelude::*;
 
+use crate::damage::{DamageEvent, DamageOverTime, DamageSource, DamageSystems, DamageType};
+use crate::global::CircleCollider;
+use crate::physics::{started_collision, CollisionLayer};
+use crate::planets::Effect;
+use crate::player::{ColorId, Player};
+use crate::sfx::SFX;
+use crate::upgrades::Upgrades;
+use crate::{AppState, FirstPass, GLOW_FACTOR};
 
 pub const ARROW_SPEED: f32 = 300.0;
 pub const ARROW_COOLDOWN: f32 = 0.5;
 pub const MAX_ARROW_SPEED: f32 = 600.0;
+pub const ARROW_DAMPING: f32 = 0.6;
+pub const QUIVER_SIZE: u32 = 8;
+pub const FULL_CHARGE: f32 = 2.0;
+pub const MAX_CHARGE: f32 = FULL_CHARGE + 2.0;
+pub const PERFECT_WINDOW_START: f32 = 1.55;
+pub const PERFECT_WINDOW_END: f32 = 1.8;
+pub const CRIT_MULTIPLIER: f32 = 2.0;
+pub const FAN_ANGLE: f32 = 0.12;
+
+const ARROW_REST_SPEED: f32 = 40.0;
+/// Even a tap of the bow sends the arrow well clear of the player before it settles.
+const MIN_LAUNCH_POWER: f32 = 0.5;
+const PICKUP_RADIUS: f32 = 15.0;
+const PICKUP_RETURN_SPEED: f32 = 500.0;
+const PICKUP_GLOW: f32 = 2.0;
+const OVERCHARGE_DRAIN: f32 = 0.75;
+const MIN_OVERCHARGE_DAMAGE: f32 = 0.5;
+const WOBBLE_AMPLITUDE: f32 = 0.25;
+const WOBBLE_FREQUENCY: f32 = 9.0;
+const BASE_SPREAD: f32 = 0.35;
+const MIN_SPREAD: f32 = 0.01;
+const MOVEMENT_SPREAD: f32 = 0.0015;
 
 #[derive(Component)]
 pub struct Arrow {
-    pub damage: f32
-}
\ No newline at end of file
+    pub damage: f32,
+    pub crit: bool,
+    /// Effect of the crystal equipped when the arrow was fired.
+    pub effect: Option<Effect>,
+    /// ...and its color.
+    pub color: Option<ColorId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargeState {
+    Charging,
+    Perfect,
+    Full,
+    Overcharged,
+}
+
+impl ChargeState {
+    pub fn from_charge(charge_time: f32) -> Self {
+        if (PERFECT_WINDOW_START..PERFECT_WINDOW_END).contains(&charge_time) {
+            ChargeState::Perfect
+        } else if charge_time < FULL_CHARGE {
+            ChargeState::Charging
+        } else if charge_time < FULL_CHARGE + 0.1 {
+            ChargeState::Full
+        } else {
+            ChargeState::Overcharged
+        }
+    }
+
+    /// Tint of the charging arrow, so the perfect window can be read without any HUD.
+    pub fn color(&self) -> Color {
+        match self {
+            ChargeState::Perfect => Color::linear_rgb(GLOW_FACTOR * 2., GLOW_FACTOR * 1.6, 0.),
+            ChargeState::Overcharged => Color::linear_rgb(GLOW_FACTOR, GLOW_FACTOR * 0.2, GLOW_FACTOR * 0.2),
+            _ => Color::linear_rgb(GLOW_FACTOR, GLOW_FACTOR, GLOW_FACTOR),
+        }
+    }
+}
+
+/// Damage of an arrow released after `charge_time` seconds, and whether it was a critical hit.
+pub fn arrow_damage(charge_time: f32) -> (f32, bool) {
+    match ChargeState::from_charge(charge_time) {
+        ChargeState::Perfect => (charge_time * CRIT_MULTIPLIER, true),
+        ChargeState::Overcharged => {
+            let drained = FULL_CHARGE - (charge_time - FULL_CHARGE) * OVERCHARGE_DRAIN;
+            (drained.max(MIN_OVERCHARGE_DAMAGE), false)
+        }
+        _ => (charge_time, false),
+    }
+}
+
+/// Draw power of an arrow released after `charge_time` seconds.
+pub fn launch_power(charge_time: f32) -> f32 {
+    charge_time.clamp(MIN_LAUNCH_POWER, FULL_CHARGE)
+}
+
+/// Launch velocity of an arrow fired at angle `alpha` with the given draw power.
+pub fn arrow_velocity(alpha: f32, power: f32) -> Vec2 {
+    Vec2::new(
+        (ARROW_SPEED * alpha.cos() * power).clamp(-MAX_ARROW_SPEED, MAX_ARROW_SPEED),
+        (ARROW_SPEED * alpha.sin() * power).clamp(-MAX_ARROW_SPEED, MAX_ARROW_SPEED),
+    )
+}
+
+/// Distance an arrow launched at `speed` covers before damping brings it to rest.
+pub fn arrow_travel_distance(speed: f32) -> f32 {
+    ((speed - ARROW_REST_SPEED) / ARROW_DAMPING).max(0.)
+}
+
+/// Maximum random deviation (in radians) of a shot. Tightens with charge and
+/// loosens with the player's speed; `focus` scales the whole cone.
+pub fn aim_spread(charge_time: f32, player_speed: f32, focus: f32) -> f32 {
+    let charge = (charge_time / FULL_CHARGE).clamp(0., 1.);
+    ((BASE_SPREAD * (1. - charge) + player_speed * MOVEMENT_SPREAD) * focus).max(MIN_SPREAD)
+}
+
+/// Angles of every arrow in a fan of `count` arrows centered on `alpha`.
+pub fn fan_angles(alpha: f32, count: u32) -> impl Iterator<Item = f32> {
+    let half = (count as f32 - 1.) / 2.;
+    (0..count).map(move |i| alpha + (i as f32 - half) * FAN_ANGLE)
+}
+
+/// Angle offset added to the aim while the bow is held past full charge.
+pub fn aim_wobble(charge_time: f32, elapsed: f32) -> f32 {
+    let overcharge = ((charge_time - FULL_CHARGE) / (MAX_CHARGE - FULL_CHARGE)).clamp(0., 1.);
+    let wave = (elapsed * WOBBLE_FREQUENCY).sin() + 0.5 * (elapsed * WOBBLE_FREQUENCY * 2.3).cos();
+    wave * WOBBLE_AMPLITUDE * overcharge
+}
+
+/// Arrow lying in the world, waiting to be picked up again.
+#[derive(Component)]
+pub struct ArrowPickup;
+
+#[derive(Resource)]
+pub struct Quiver {
+    pub current: u32,
+    pub capacity: u32,
+}
+
+impl Default for Quiver {
+    fn default() -> Self {
+        Quiver { current: QUIVER_SIZE, capacity: QUIVER_SIZE }
+    }
+}
+
+pub struct ArrowPlugin;
+
+impl Plugin for ArrowPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                arrow_hits.before(DamageSystems::Resolve),
+                settle_arrows,
+                return_arrow_pickups,
+                collect_arrow_pickups,
+            ).run_if(in_state(AppState::InGame)))
+            .insert_resource(Quiver::default());
+    }
+}
+
+/// Turns a flying arrow into a pickup that stays where it is.
+pub fn drop_arrow(commands: &mut Commands, arrow: Entity) {
+    commands
+        .entity(arrow)
+        .remove::<(Arrow, RigidBody, Collider, Velocity, ActiveEvents, Restitution, Damping, ParticleEffect)>()
+        .remove::<(CollisionLayer, CollisionGroups, SolverGroups)>()
+        .insert((ArrowPickup, RenderLayers::layer(0), FirstPass))
+        .entry::<Sprite>()
+        .and_modify(|mut sprite| sprite.color = Color::linear_rgb(PICKUP_GLOW, PICKUP_GLOW, PICKUP_GLOW));
+}
+
+/// Turns arrow collisions with enemies and planets into damage events.
+fn arrow_hits(
+    mut collision_events: EventReader<CollisionEvent>,
+    arrow_query: Query<(&Arrow, &Velocity, &Transform)>,
+    layer_query: Query<&CollisionLayer>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut commands: Commands,
+) {
+    for event in collision_events.read() {
+        let Some((arrow_ent, target, target_layer)) = started_collision(event, &layer_query, CollisionLayer::Arrow) else {
+            continue;
+        };
+        let Ok((arrow, velocity, transform)) = arrow_query.get(arrow_ent) else {
+            continue;
+        };
+        let is_enemy = target_layer == CollisionLayer::Enemy;
+
+        // the impact itself is always physical, the crystal's element lingers after it
+        damage_events.write(DamageEvent {
+            target,
+            source: DamageSource::Arrow,
+            attacker: None,
+            damage_type: DamageType::Physical,
+            color: arrow.color,
+            amount: arrow.damage,
+            crit: arrow.crit,
+            knockback: velocity.linvel,
+            position: transform.translation.truncate(),
+        });
+        if is_enemy
+            && let Some(effect) = &arrow.effect
+            && let damage_type = DamageType::from_effect(effect.effect_type)
+            && let Some((dps, duration)) = damage_type.damage_over_time(effect.level)
+        {
+            commands.entity(target).try_insert(DamageOverTime::new(damage_type, dps, duration));
+        }
+        // arrows ricochet off planets and only drop once they come to rest
+        if target_layer == CollisionLayer::Planet && velocity.linvel.length() > ARROW_REST_SPEED {
+            continue;
+        }
+        drop_arrow(&mut commands, arrow_ent);
+    }
+}
+
+fn settle_arrows(
+    arrow_query: Query<(Entity, &Velocity), With<Arrow>>,
+    mut commands: Commands,
+) {
+    for (entity, velocity) in arrow_query {
+        if velocity.linvel.length() < ARROW_REST_SPEED {
+            drop_arrow(&mut commands, entity);
+        }
+    }
+}
+
+fn return_arrow_pickups(
+    pickup_query: Query<&mut Transform, (With<ArrowPickup>, Without<Player>)>,
+    player_query: Query<&Transform, With<Player>>,
+    upgrades: Res<Upgrades>,
+    quiver: Res<Quiver>,
+    time: Res<Time>,
+) {
+    if !upgrades.auto_return || quiver.current >= quiver.capacity {
+        return;
+    }
+    let player = player_query.single().unwrap();
+    for mut pickup in pickup_query {
+        let dir = (player.translation - pickup.translation).truncate().normalize_or_zero();
+        pickup.translation += (dir * PICKUP_RETURN_SPEED * time.delta_secs()).extend(0.);
+        pickup.rotation = Quat::from_rotation_z(dir.to_angle());
+    }
+}
+
+fn collect_arrow_pickups(
+    player_query: Query<(&Transform, &CircleCollider), With<Player>>,
+    pickup_query: Query<(&Transform, Entity), With<ArrowPickup>>,
+    mut quiver: ResMut<Quiver>,
+    mut commands: Commands,
+    sfx: Res<SFX>,
+) {
+    let (tr, collider) = player_query.single().unwrap();
+
+    for (pickup_tr, ent) in pickup_query {
+        if quiver.current >= quiver.capacity {
+            return;
+        }
+        if pickup_tr.translation.truncate().distance(tr.translation.truncate()) < collider.0 + PICKUP_RADIUS {
+            commands.entity(ent).despawn();
+
+            quiver.current += 1;
+            commands.spawn(AudioPlayer(sfx.xp.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crits_only_inside_the_perfect_window() {
+        assert_eq!(arrow_damage(PERFECT_WINDOW_START), (PERFECT_WINDOW_START * CRIT_MULTIPLIER, true));
+        assert!(arrow_damage((PERFECT_WINDOW_START + PERFECT_WINDOW_END) / 2.).1);
+        assert_eq!(arrow_damage(PERFECT_WINDOW_START - 0.01), (PERFECT_WINDOW_START - 0.01, false));
+        assert_eq!(arrow_damage(PERFECT_WINDOW_END), (PERFECT_WINDOW_END, false));
+        assert_eq!(ChargeState::from_charge(PERFECT_WINDOW_END), ChargeState::Charging);
+        assert_eq!(ChargeState::from_charge(FULL_CHARGE), ChargeState::Full);
+    }
+
+    #[test]
+    fn overcharging_drains_damage() {
+        assert_eq!(ChargeState::from_charge(FULL_CHARGE + 0.5), ChargeState::Overcharged);
+        let (early, _) = arrow_damage(FULL_CHARGE + 0.5);
+        let (late, crit) = arrow_damage(MAX_CHARGE);
+        assert!(late < early && early < FULL_CHARGE);
+        assert!(!crit);
+        assert_eq!(arrow_damage(MAX_CHARGE * 10.).0, MIN_OVERCHARGE_DAMAGE);
+    }
+
+    #[test]
+    fn fans_are_centered_on_the_aim() {
+        let single: Vec<f32> = fan_angles(1., 1).collect();
+        assert_eq!(single, vec![1.]);
+        let fan: Vec<f32> = fan_angles(1., 3).collect();
+        assert_eq!(fan, vec![1. - FAN_ANGLE, 1., 1. + FAN_ANGLE]);
+        let even: Vec<f32> = fan_angles(0., 4).collect();
+        assert_eq!(even.len(), 4);
+        assert!((even.iter().sum::<f32>()).abs() < 1e-6);
+        assert!(even.windows(2).all(|pair| (pair[1] - pair[0] - FAN_ANGLE).abs() < 1e-6));
+    }
+
+    #[test]
+    fn spread_tightens_with_charge_and_loosens_with_speed() {
+        assert!(aim_spread(FULL_CHARGE, 0., 1.) < aim_spread(0., 0., 1.));
+        assert!(aim_spread(FULL_CHARGE, 300., 1.) > aim_spread(FULL_CHARGE, 0., 1.));
+        assert!(aim_spread(0., 0., 0.5) < aim_spread(0., 0., 1.));
+        assert_eq!(aim_spread(FULL_CHARGE, 0., 0.), MIN_SPREAD);
+    }
+}
@@ -0,0 +1,271 @@
+use bevy::ecs::query::QueryData;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::elite::Elite;
+use crate::enemy::{calculate_intercept_point, Behaviour, Enemy};
+use crate::flocking::{Flank, Steering};
+use crate::flow_field::FlowField;
+use crate::morale::Morale;
+use crate::nest::Guard;
+use crate::player::Player;
+use crate::projectile::Shooter;
+use crate::raider::Raider;
+use crate::AppState;
+
+/// Share of the gap to the wanted velocity closed every frame.
+const STEERING: f32 = 0.1;
+/// Distance ranged enemies try to hold from the player.
+const KEEP_DISTANCE: f32 = 260.0;
+const KEEP_DISTANCE_SLACK: f32 = 40.0;
+/// Ranged enemies run when the player gets this close.
+const PANIC_DISTANCE: f32 = 140.0;
+const DASH_COOLDOWN: f32 = 3.0;
+const CHARGE_UP: f32 = 0.4;
+const DASH_DURATION: f32 = 0.5;
+const DASH_SPEED: f32 = 5.0;
+const STRAFE_DISTANCE: f32 = 180.0;
+const STRAFE_DURATION: f32 = 2.0;
+/// Close enough to ignore the flow field and head straight for the player.
+const DIRECT_RANGE: f32 = 150.0;
+/// Intercepting only happens while the flow field points roughly at the player,
+/// otherwise something is in the way.
+const CLEAR_PATH: f32 = 0.95;
+
+/// Movement an enemy performs while in a state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// At the player, following the flow field around planets.
+    Seek,
+    /// Towards where the player is going to be.
+    Intercept,
+    /// Circle the player at `distance`, closing in or backing off by `slack`.
+    Orbit { distance: f32, slack: f32 },
+    /// Circle the player without changing distance.
+    Strafe,
+    /// Straight away from the player.
+    Flee,
+    /// Brake and wind up for an attack.
+    ChargeUp,
+    /// Lunge at the player at `speed` times the normal speed, without steering.
+    Attack { speed: f32 },
+    /// Back off to `distance` while circling, to keep shooting from safety.
+    Kite { distance: f32 },
+    /// Gather at `rally`, out of the player's way.
+    Regroup { rally: Vec2 },
+    /// Circle `center` at `radius`, ignoring the player.
+    Patrol { center: Vec2, radius: f32 },
+    /// Straight at `target`, ignoring the player.
+    Approach { target: Vec2 },
+}
+
+/// When to leave the current state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    /// Seconds spent in the state.
+    After(f32),
+    /// Player closer than this.
+    Within(f32),
+    /// Player further than this.
+    Beyond(f32),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Transition {
+    pub when: Condition,
+    /// Index of the next state in the same brain.
+    pub to: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct State {
+    pub action: Action,
+    /// Checked in order, the first one that holds is taken.
+    pub transitions: &'static [Transition],
+}
+
+pub const CHASER: &[State] = &[
+    State { action: Action::Seek, transitions: &[] },
+];
+
+pub const INTERCEPTOR: &[State] = &[
+    State { action: Action::Intercept, transitions: &[] },
+];
+
+pub const GUNNER: &[State] = &[
+    State {
+        action: Action::Orbit { distance: KEEP_DISTANCE, slack: KEEP_DISTANCE_SLACK },
+        transitions: &[Transition { when: Condition::Within(PANIC_DISTANCE), to: 1 }],
+    },
+    State {
+        action: Action::Flee,
+        transitions: &[Transition { when: Condition::Beyond(KEEP_DISTANCE), to: 0 }],
+    },
+];
+
+pub const CHARGER: &[State] = &[
+    State { action: Action::Seek, transitions: &[Transition { when: Condition::After(DASH_COOLDOWN), to: 1 }] },
+    State { action: Action::ChargeUp, transitions: &[Transition { when: Condition::After(CHARGE_UP), to: 2 }] },
+    State { action: Action::Attack { speed: DASH_SPEED }, transitions: &[Transition { when: Condition::After(DASH_DURATION), to: 0 }] },
+];
+
+pub const JUGGERNAUT: &[State] = &[
+    State { action: Action::Seek, transitions: &[Transition { when: Condition::Within(STRAFE_DISTANCE), to: 1 }] },
+    State { action: Action::Strafe, transitions: &[Transition { when: Condition::After(STRAFE_DURATION), to: 0 }] },
+];
+
+impl Behaviour {
+    pub fn states(self) -> &'static [State] {
+        match self {
+            Behaviour::Chaser => CHASER,
+            Behaviour::Interceptor => INTERCEPTOR,
+            Behaviour::Gunner => GUNNER,
+            Behaviour::Charger => CHARGER,
+            Behaviour::Juggernaut => JUGGERNAUT,
+        }
+    }
+}
+
+/// State machine driving an enemy's movement.
+#[derive(Component)]
+#[require(Steering, Morale)]
+pub struct Brain {
+    states: &'static [State],
+    current: usize,
+    elapsed: f32,
+}
+
+impl Brain {
+    pub fn new(states: &'static [State]) -> Self {
+        Brain { states, current: 0, elapsed: 0. }
+    }
+
+    pub fn action(&self) -> Action {
+        self.states[self.current].action
+    }
+
+    fn enter(&mut self, state: usize) {
+        self.current = state;
+        self.elapsed = 0.;
+    }
+
+    /// Takes the first transition that holds, if any.
+    fn transition(&mut self, distance: f32) {
+        let next = self.states[self.current].transitions.iter().find(|t| match t.when {
+            Condition::After(seconds) => self.elapsed >= seconds,
+            Condition::Within(range) => distance < range,
+            Condition::Beyond(range) => distance > range,
+        });
+        if let Some(next) = next {
+            self.enter(next.to);
+        }
+    }
+}
+
+pub struct BehaviourPlugin;
+
+impl Plugin for BehaviourPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, run_brains.run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Everything an enemy's movement depends on.
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct BrainQuery {
+    transform: &'static Transform,
+    velocity: &'static mut Velocity,
+    enemy: &'static Enemy,
+    brain: &'static mut Brain,
+    elite: Option<&'static Elite>,
+    steering: &'static Steering,
+    flank: Option<&'static Flank>,
+    morale: &'static Morale,
+    ranged: Has<Shooter>,
+    guard: Option<&'static Guard>,
+    raider: Option<&'static Raider>,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn run_brains(
+    enemy_query: Query<BrainQuery, Without<Player>>,
+    player_query: Query<(&Transform, &Velocity), (With<Player>, Without<Enemy>)>,
+    flow_field: Res<FlowField>,
+    time: Res<Time>,
+) {
+    let (player_tr, player_vel) = player_query.single().unwrap();
+    let player_pos = player_tr.translation.truncate();
+    for BrainQueryItem { transform, velocity: mut vel, enemy, mut brain, elite, steering, flank, morale, ranged, guard, raider } in enemy_query {
+        let position = transform.translation.truncate();
+        let to_player = player_pos - position;
+        brain.transition(to_player.length());
+        let entered = brain.elapsed == 0.;
+        brain.elapsed += time.delta_secs();
+
+        let speed = enemy.speed() * elite.map_or(1., Elite::speed_multiplier);
+        let dir = to_player.normalize_or_zero();
+        // way around the planets, straight at the player once close
+        let path = if to_player.length() < DIRECT_RANGE {
+            dir
+        } else {
+            flow_field.direction(position).unwrap_or(dir)
+        };
+        // flankers swing wide while nothing blocks the way
+        let flanking = flank
+            .filter(|_| path.dot(dir) > CLEAR_PATH)
+            .and_then(|flank| flank.approach(position, player_pos, player_vel.linvel, speed));
+        // shaken enemies forget their plan until they rally, guards keep to their nest
+        // and raiders go for the planets
+        let action = morale
+            .action(ranged)
+            .or(guard.map(|guard| Action::Patrol { center: guard.home, radius: guard.radius }))
+            .or(raider.and_then(Raider::action))
+            .unwrap_or(brain.action());
+        let target = match action {
+            Action::Seek | Action::Intercept if let Some(flanking) = flanking => flanking * speed,
+            Action::Seek => path * speed,
+            Action::Intercept if path.dot(dir) > CLEAR_PATH => {
+                calculate_intercept_point(position, player_pos, player_vel.linvel, speed)
+                    .map_or(dir, |aim| (aim - position).normalize_or(dir))
+                    * speed
+            }
+            Action::Intercept => path * speed,
+            Action::Orbit { distance, slack } => {
+                // back off or close in to stay at range, circling the player meanwhile
+                let radial = if to_player.length() > distance + slack {
+                    path
+                } else if to_player.length() < distance - slack {
+                    -dir
+                } else {
+                    Vec2::ZERO
+                };
+                (radial + dir.perp() * 0.5) * speed
+            }
+            Action::Strafe => dir.perp() * speed,
+            Action::Flee => -dir * speed,
+            Action::ChargeUp => Vec2::ZERO,
+            Action::Kite { distance } => {
+                let radial = if to_player.length() < distance { -dir } else { Vec2::ZERO };
+                (radial + dir.perp() * 0.5) * speed
+            }
+            Action::Regroup { rally } => (rally - position).clamp_length_max(speed),
+            Action::Patrol { center, radius } => {
+                let offset = position - center;
+                let out = offset.normalize_or(Vec2::X);
+                let radial = ((radius - offset.length()) / radius).clamp(-1., 1.);
+                (out * radial + out.perp() * 0.5) * speed
+            }
+            Action::Approach { target } => (target - position).normalize_or_zero() * speed,
+            Action::Attack { speed: multiplier } => {
+                // committed to the lunge, no steering
+                if entered {
+                    vel.linvel = dir * speed * multiplier;
+                }
+                continue;
+            }
+        };
+        vel.linvel = vel.linvel.lerp(target + steering.0 * speed, STEERING);
+    }
+}
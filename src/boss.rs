@@ -0,0 +1,420 @@
+use std::f32::consts::TAU;
+use bevy::color::palettes::css::DARK_GRAY;
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_hanabi::ParticleEffect;
+use bevy_rapier2d::prelude::*;
+use rand::prelude::*;
+
+use crate::damage::{DamageDealt, DamageSystems, Resistances};
+use crate::enemy::{calculate_intercept_point, spawn_enemy, Enemy, HP};
+use crate::global::{adjusted_glow, regular_polygon_vertices, CircleCollider, ScreenShake};
+use crate::particles::ParticleHandles;
+use crate::physics::CollisionLayer;
+use crate::planets::{Effect, EffectType};
+use crate::player::{ColorId, Crystal, Inventory, Player};
+use crate::projectile::{spawn_projectile, MUZZLE_OFFSET, PROJECTILE_SPEED};
+use crate::sfx::SFX;
+use crate::upgrades::LevelUp;
+use crate::world::{is_position_safe, EnemiesCounter, PlanetData};
+use crate::xp::spawn_orbs;
+use crate::{AppState, FirstPass, ENEMY_COLOR, GLOW_FACTOR};
+
+/// A boss shows up every this many levels.
+const BOSS_LEVEL_INTERVAL: i32 = 5;
+const BOSS_SIDES: i32 = 12;
+const BOSS_RADIUS: f32 = 110.0;
+const BOSS_HP: f32 = 150.0;
+const BOSS_HP_PER_LEVEL: f32 = 30.0;
+/// The hull shrugs off most damage, weak points are where it hurts.
+const BOSS_RESISTANCE: f32 = 0.5;
+const SPAWN_DISTANCE: f32 = 650.0;
+const ORBIT_DISTANCE: f32 = 320.0;
+const BOSS_SPEED: f32 = 60.0;
+const WEAK_POINTS: usize = 3;
+const WEAK_POINT_RADIUS: f32 = 14.0;
+const WEAK_POINT_MULTIPLIER: f32 = 4.0;
+const WEAK_POINT_COLOR: Color = Color::srgb(1., 0.9, 0.2);
+/// HP fractions where the fight moves on to the next phase.
+const SUMMON_THRESHOLD: f32 = 0.66;
+const ENRAGE_THRESHOLD: f32 = 0.33;
+const RING_SHOTS: u32 = 12;
+const RING_COOLDOWN: f32 = 3.0;
+const ENRAGED_RING_COOLDOWN: f32 = 1.5;
+const VOLLEY_COOLDOWN: f32 = 2.0;
+const VOLLEY_SHOTS: u32 = 3;
+const VOLLEY_SPREAD: f32 = 0.2;
+const SUMMON_COOLDOWN: f32 = 6.0;
+const SUMMON_COUNT: u32 = 3;
+const MAX_MINIONS: usize = 8;
+const DASH_COOLDOWN: f32 = 4.0;
+const DASH_DURATION: f32 = 0.6;
+const DASH_SPEED: f32 = 5.0;
+const BOSS_XP: f64 = 200.0;
+const BOSS_CRYSTALS: usize = 2;
+const BOSS_CRYSTAL_LEVEL: i32 = 3;
+const BAR_WIDTH: f32 = 600.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossPhase {
+    /// Circles the player and fires rings of shots.
+    Barrage,
+    /// Calls in minions and fires aimed volleys.
+    Summon,
+    /// Spins up, dashes at the player and fires rings faster.
+    Enraged,
+}
+
+impl BossPhase {
+    fn from_health(fraction: f32) -> Self {
+        if fraction > SUMMON_THRESHOLD {
+            BossPhase::Barrage
+        } else if fraction > ENRAGE_THRESHOLD {
+            BossPhase::Summon
+        } else {
+            BossPhase::Enraged
+        }
+    }
+
+    fn spin(self) -> f32 {
+        match self {
+            BossPhase::Barrage => 0.4,
+            BossPhase::Summon => 0.8,
+            BossPhase::Enraged => 2.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Boss {
+    phase: BossPhase,
+    attack_cooldown: f32,
+    summon_cooldown: f32,
+    dash_cooldown: f32,
+    dashing: f32,
+}
+
+/// Collider on a boss that passes hits on to it, multiplied.
+#[derive(Component)]
+pub struct WeakPoint {
+    pub multiplier: f32,
+}
+
+/// Enemy summoned by a boss.
+#[derive(Component)]
+struct Minion;
+
+/// Top of screen health bar of a boss.
+#[derive(Component)]
+struct BossBar(Entity);
+
+#[derive(Component)]
+struct BossBarFill;
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                spawn_bosses,
+                boss_movement,
+                boss_attacks,
+                update_boss_bar,
+                boss_loot.in_set(DamageSystems::React),
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_bosses(
+    mut level_ups: EventReader<LevelUp>,
+    boss_query: Query<(), With<Boss>>,
+    player_query: Query<&Transform, With<Player>>,
+    planet_data: Res<PlanetData>,
+    mut enemies: ResMut<EnemiesCounter>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    assets: Res<AssetServer>,
+) {
+    for LevelUp(level) in level_ups.read() {
+        if level % BOSS_LEVEL_INTERVAL != 0 || !boss_query.is_empty() {
+            continue;
+        }
+        let player = player_query.single().unwrap().translation.truncate();
+        let mut rng = rand::rng();
+        let mut position = player + Vec2::from_angle(rng.random_range(0.0..TAU)) * SPAWN_DISTANCE;
+        for _ in 0..20 {
+            if is_position_safe(position, &planet_data) {
+                break;
+            }
+            position = player + Vec2::from_angle(rng.random_range(0.0..TAU)) * SPAWN_DISTANCE;
+        }
+        spawn_boss(&mut commands, &mut meshes, &mut materials, &mut enemies, &assets, *level, position);
+    }
+}
+
+/// Spawns a boss dodecagon with weak points on its corners, and its HUD bar.
+fn spawn_boss(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    enemies: &mut ResMut<EnemiesCounter>,
+    assets: &Res<AssetServer>,
+    level: i32,
+    position: Vec2,
+) {
+    info!("Spawning boss at level {}.", level);
+    let hp = BOSS_HP + BOSS_HP_PER_LEVEL * level as f32;
+    let vertices = regular_polygon_vertices(BOSS_RADIUS, BOSS_SIDES as usize);
+
+    let id = commands.spawn((
+        Enemy::new(BOSS_SIDES),
+        Boss {
+            phase: BossPhase::Barrage,
+            attack_cooldown: RING_COOLDOWN,
+            summon_cooldown: 0.,
+            dash_cooldown: DASH_COOLDOWN,
+            dashing: 0.,
+        },
+        Mesh2d(meshes.add(RegularPolygon::new(BOSS_RADIUS, BOSS_SIDES as u32))),
+        MeshMaterial2d(materials.add(ColorMaterial::from_color(ENEMY_COLOR))),
+        HP {
+            current: hp,
+            max: hp,
+        },
+        Resistances { physical: BOSS_RESISTANCE, poison: BOSS_RESISTANCE, fire: BOSS_RESISTANCE },
+        CircleCollider(BOSS_RADIUS),
+        Transform::from_translation(position.extend(0.)),
+        Collider::convex_hull(&vertices.iter().map(|e| Vect::from(*e)).collect::<Vec<Vect>>()).unwrap(),
+        RigidBody::Dynamic,
+        CollisionLayer::Enemy.bundle(),
+        Velocity::angular(BossPhase::Barrage.spin()),
+        RenderLayers::layer(0),
+        FirstPass,
+    )).with_children(|parent| {
+        for i in 0..WEAK_POINTS {
+            let vertex = vertices[i * vertices.len() / WEAK_POINTS];
+            parent.spawn((
+                WeakPoint { multiplier: WEAK_POINT_MULTIPLIER },
+                Mesh2d(meshes.add(Circle::new(WEAK_POINT_RADIUS))),
+                MeshMaterial2d(materials.add(ColorMaterial::from_color(adjusted_glow(WEAK_POINT_COLOR, GLOW_FACTOR / 2.)))),
+                Transform::from_translation(vertex.extend(0.1)),
+                Collider::ball(WEAK_POINT_RADIUS),
+                CollisionLayer::Enemy.bundle(),
+                RenderLayers::layer(0),
+                FirstPass,
+            ));
+        }
+    }).id();
+    enemies.0 += 1;
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                top: Val::Px(130.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BossBar(id),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("THE DODECAGON"),
+                TextFont {
+                    font: assets.load("Kenneymini.ttf"),
+                    font_size: 30.0,
+                    ..default()
+                },
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(20.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::Srgba(DARK_GRAY)),
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.9, 0.1, 0.1)),
+                        BossBarFill,
+                    ));
+                });
+        });
+}
+
+fn boss_movement(
+    boss_query: Query<(&Transform, &mut Velocity, &mut Boss, &HP)>,
+    player_query: Query<&Transform, (With<Player>, Without<Boss>)>,
+    mut shake: ResMut<ScreenShake>,
+    time: Res<Time>,
+) {
+    let player = player_query.single().unwrap().translation.truncate();
+    for (transform, mut velocity, mut boss, hp) in boss_query {
+        let phase = BossPhase::from_health(hp.current / hp.max);
+        if phase != boss.phase {
+            info!("Boss enters the {:?} phase.", phase);
+            boss.phase = phase;
+            boss.summon_cooldown = 0.;
+            shake.trauma = shake.trauma.max(6.0);
+        }
+        velocity.angvel = phase.spin();
+
+        let to_player = player - transform.translation.truncate();
+        let dir = to_player.normalize_or_zero();
+        if boss.dashing > 0. {
+            boss.dashing -= time.delta_secs();
+            continue;
+        }
+        if phase == BossPhase::Enraged {
+            boss.dash_cooldown -= time.delta_secs();
+            if boss.dash_cooldown <= 0. {
+                boss.dash_cooldown = DASH_COOLDOWN;
+                boss.dashing = DASH_DURATION;
+                velocity.linvel = dir * BOSS_SPEED * DASH_SPEED;
+                continue;
+            }
+        }
+        // hold the orbit distance while circling the player
+        let radial = (to_player.length() - ORBIT_DISTANCE) / ORBIT_DISTANCE;
+        let target = (dir * radial.clamp(-1., 1.) + dir.perp() * 0.6) * BOSS_SPEED;
+        velocity.linvel = velocity.linvel.lerp(target, 0.05);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn boss_attacks(
+    boss_query: Query<(Entity, &Transform, &mut Boss)>,
+    player_query: Query<(&Transform, &Velocity), With<Player>>,
+    minion_query: Query<(), With<Minion>>,
+    mut enemies: ResMut<EnemiesCounter>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    particle_handles: Res<ParticleHandles>,
+    sfx: Res<SFX>,
+    time: Res<Time>,
+) {
+    let (player_tr, player_vel) = player_query.single().unwrap();
+    let target = player_tr.translation.truncate();
+    let mut rng = rand::rng();
+    for (owner, transform, mut boss) in boss_query {
+        let origin = transform.translation.truncate();
+        boss.attack_cooldown -= time.delta_secs();
+        if boss.attack_cooldown <= 0. {
+            let shots: Vec<Vec2> = match boss.phase {
+                BossPhase::Barrage | BossPhase::Enraged => {
+                    // rotate the ring with the hull so the gaps move
+                    let start = transform.rotation.to_euler(EulerRot::XYZ).2;
+                    (0..RING_SHOTS).map(|i| Vec2::from_angle(start + TAU * i as f32 / RING_SHOTS as f32)).collect()
+                }
+                BossPhase::Summon => {
+                    let aim = calculate_intercept_point(origin, target, player_vel.linvel, PROJECTILE_SPEED).unwrap_or(target);
+                    let aim_dir = (aim - origin).normalize_or_zero();
+                    (0..VOLLEY_SHOTS)
+                        .map(|i| Vec2::from_angle((i as f32 - (VOLLEY_SHOTS - 1) as f32 / 2.) * VOLLEY_SPREAD).rotate(aim_dir))
+                        .collect()
+                }
+            };
+            boss.attack_cooldown = match boss.phase {
+                BossPhase::Barrage => RING_COOLDOWN,
+                BossPhase::Summon => VOLLEY_COOLDOWN,
+                BossPhase::Enraged => ENRAGED_RING_COOLDOWN,
+            };
+            for dir in shots {
+                spawn_projectile(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    &particle_handles,
+                    owner,
+                    origin + dir * (BOSS_RADIUS + MUZZLE_OFFSET),
+                    dir,
+                );
+            }
+            commands.spawn(AudioPlayer(sfx.shoot.clone()));
+        }
+
+        if boss.phase != BossPhase::Summon {
+            continue;
+        }
+        boss.summon_cooldown -= time.delta_secs();
+        if boss.summon_cooldown > 0. {
+            continue;
+        }
+        boss.summon_cooldown = SUMMON_COOLDOWN;
+        let count = (SUMMON_COUNT as usize).min(MAX_MINIONS.saturating_sub(minion_query.iter().count()));
+        for _ in 0..count {
+            let dir = Vec2::from_angle(rng.random_range(0.0..TAU));
+            let position = origin + dir * (BOSS_RADIUS + 40.);
+            let minion = spawn_enemy(&mut commands, &mut meshes, &mut materials, &mut enemies, Enemy::new(rng.random_range(3..=4)), position);
+            commands.entity(minion).insert(Minion);
+            commands.spawn((
+                ParticleEffect::new(particle_handles.enemy_death.clone()),
+                Transform::from_translation(position.extend(0.)),
+            ));
+        }
+    }
+}
+
+fn update_boss_bar(
+    bar_query: Query<(Entity, &BossBar)>,
+    mut fill_query: Query<&mut Node, With<BossBarFill>>,
+    boss_query: Query<&HP, With<Boss>>,
+    mut commands: Commands,
+) {
+    for (entity, bar) in bar_query {
+        let Ok(hp) = boss_query.get(bar.0) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        // one boss at a time, so there is only ever one fill
+        for mut node in &mut fill_query {
+            node.width = Val::Percent(100. * hp.current.max(0.) / hp.max);
+        }
+    }
+}
+
+/// Guaranteed high level crystals and a pile of XP for beating a boss.
+fn boss_loot(
+    mut dealt: EventReader<DamageDealt>,
+    boss_query: Query<&Transform, With<Boss>>,
+    mut inventory: ResMut<Inventory>,
+    mut commands: Commands,
+    particle_handles: Res<ParticleHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut rng = rand::rng();
+    for event in dealt.read() {
+        if !event.killed {
+            continue;
+        }
+        let Ok(transform) = boss_query.get(event.target) else {
+            continue;
+        };
+        info!("Boss defeated.");
+        spawn_orbs(&mut commands, BOSS_XP, transform.translation, &particle_handles, &mut meshes, &mut materials);
+        for _ in 0..BOSS_CRYSTALS {
+            let crystal = Crystal {
+                color: ColorId::new(rng.random(), rng.random(), rng.random()),
+                effect: Effect { effect_type: *EffectType::ALL.choose(&mut rng).unwrap(), level: BOSS_CRYSTAL_LEVEL },
+                phase: rng.random(),
+                resonance: rng.random(),
+            };
+            info!("Boss dropped a {:?} crystal.", crystal.effect.effect_type);
+            inventory.crystals.push(crystal);
+        }
+    }
+}
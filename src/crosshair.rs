@@ -0,0 +1,188 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+
+use bevy_rapier2d::prelude::Velocity;
+
+use crate::arrow::{arrow_velocity, launch_power, ChargeState, FULL_CHARGE, PERFECT_WINDOW_END, PERFECT_WINDOW_START};
+use crate::enemy::{calculate_intercept_point, Enemy};
+use crate::global::{world_to_overlay, Settings};
+use crate::player::{ChargingArrow, Inventory};
+use crate::{AppState, CursorCamera, FirstPass, SCALE};
+
+const CROSSHAIR_GAP: f32 = 6.0;
+const CROSSHAIR_LENGTH: f32 = 10.0;
+const RING_RADIUS: f32 = 24.0;
+const HITMARKER_DURATION: f32 = 0.15;
+const HITMARKER_SIZE: f32 = 9.0;
+const LEAD_MARKER_SIZE: f32 = 7.0;
+
+/// Gizmos drawn by the unpixelated cursor camera on render layer 1.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct CrosshairGizmos;
+
+#[derive(Resource, Default)]
+pub struct Hitmarker {
+    timer: f32,
+    crit: bool,
+}
+
+impl Hitmarker {
+    pub fn trigger(&mut self, crit: bool) {
+        self.timer = HITMARKER_DURATION;
+        self.crit = crit;
+    }
+}
+
+pub struct CrosshairPlugin;
+
+impl Plugin for CrosshairPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_gizmo_group::<CrosshairGizmos>()
+            .add_systems(Startup, setup_crosshair_gizmos)
+            .add_systems(OnEnter(AppState::InGame), hide_cursor)
+            .add_systems(Update, (draw_crosshair, draw_lead_indicator).run_if(in_state(AppState::InGame)))
+            .insert_resource(Hitmarker::default());
+    }
+}
+
+fn setup_crosshair_gizmos(mut config_store: ResMut<GizmoConfigStore>) {
+    let (config, _) = config_store.config_mut::<CrosshairGizmos>();
+    config.render_layers = RenderLayers::layer(1);
+    config.line.width = 2.0;
+}
+
+fn hide_cursor(mut window: Query<&mut Window, With<PrimaryWindow>>) {
+    let mut window = window.single_mut().unwrap();
+    window.cursor_options.visible = false;
+}
+
+fn draw_crosshair(
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<CursorCamera>>,
+    charging_query: Query<&ChargingArrow>,
+    inventory: Res<Inventory>,
+    mut hitmarker: ResMut<Hitmarker>,
+    time: Res<Time<Real>>,
+    mut gizmos: Gizmos<CrosshairGizmos>,
+) {
+    let window = window.single().unwrap();
+    let Some(cursor_pos) = window.cursor_position() else {
+        return;
+    };
+    let (camera, camera_transform) = camera_q.single().unwrap();
+    let Ok(pos) = camera.viewport_to_world_2d(camera_transform, cursor_pos) else {
+        return;
+    };
+
+    // the crosshair hints at the crystal currently socketed in the bow
+    let color = inventory
+        .equipped
+        .as_ref()
+        .map_or(Color::WHITE, |crystal| crystal.color.to_bevy());
+
+    for dir in [Vec2::X, Vec2::Y, Vec2::NEG_X, Vec2::NEG_Y] {
+        gizmos.line_2d(
+            pos + dir * CROSSHAIR_GAP,
+            pos + dir * (CROSSHAIR_GAP + CROSSHAIR_LENGTH),
+            color,
+        );
+    }
+    gizmos.circle_2d(pos, 1., color);
+
+    if let Ok(charging) = charging_query.single() {
+        let state = ChargeState::from_charge(charging.charge_time);
+        let fill = (charging.charge_time / FULL_CHARGE).min(1.) * TAU;
+        let window_start = PERFECT_WINDOW_START / FULL_CHARGE * TAU;
+        let window_end = PERFECT_WINDOW_END / FULL_CHARGE * TAU;
+
+        gizmos.circle_2d(pos, RING_RADIUS, Color::srgba(1., 1., 1., 0.15));
+        gizmos
+            .arc_2d(
+                Isometry2d::new(pos, Rot2::radians(-window_end)),
+                window_end - window_start,
+                RING_RADIUS + 4.,
+                Color::srgb(1., 0.8, 0.),
+            )
+            .resolution(16);
+
+        let ring_color = match state {
+            ChargeState::Perfect => Color::srgb(1., 0.8, 0.),
+            ChargeState::Overcharged => {
+                let pulse = (time.elapsed_secs() * 20.).sin() * 0.5 + 0.5;
+                Color::srgb(1., 0.2 * pulse, 0.2 * pulse)
+            }
+            _ => color,
+        };
+        gizmos
+            .arc_2d(Isometry2d::new(pos, Rot2::radians(-fill)), fill, RING_RADIUS, ring_color)
+            .resolution(48);
+    }
+
+    if hitmarker.timer > 0. {
+        hitmarker.timer -= time.delta_secs();
+        let hit_color = if hitmarker.crit { Color::srgb(1., 0.8, 0.) } else { Color::WHITE };
+        let size = HITMARKER_SIZE * if hitmarker.crit { 1.5 } else { 1. };
+        for dir in [Vec2::ONE, Vec2::new(1., -1.), Vec2::NEG_ONE, Vec2::new(-1., 1.)] {
+            let dir = dir.normalize();
+            gizmos.line_2d(pos + dir * size * 0.5, pos + dir * size * 1.5, hit_color);
+        }
+    }
+}
+
+/// Marks where the arrow being charged would meet the enemy closest to the cursor.
+fn draw_lead_indicator(
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<FirstPass>>,
+    cursor_camera_q: Query<(&Camera, &GlobalTransform), With<CursorCamera>>,
+    charging_query: Query<(&Transform, &ChargingArrow)>,
+    enemy_query: Query<(&Transform, &Velocity), With<Enemy>>,
+    settings: Res<Settings>,
+    mut gizmos: Gizmos<CrosshairGizmos>,
+) {
+    if !settings.aim_assist {
+        return;
+    }
+    let Ok((arrow_tr, charging)) = charging_query.single() else {
+        return;
+    };
+    let window = window.single().unwrap();
+    let Some(cursor_pos) = window.cursor_position() else {
+        return;
+    };
+    let (camera, camera_transform) = camera_q.single().unwrap();
+    let cursor_camera = cursor_camera_q.single().unwrap();
+    let Ok(cursor_world) = camera.viewport_to_world_2d(camera_transform, cursor_pos * SCALE) else {
+        return;
+    };
+
+    let Some((enemy_tr, enemy_vel)) = enemy_query.iter().min_by(|(a, _), (b, _)| {
+        let da = a.translation.truncate().distance_squared(cursor_world);
+        let db = b.translation.truncate().distance_squared(cursor_world);
+        da.total_cmp(&db)
+    }) else {
+        return;
+    };
+
+    let shooter = arrow_tr.translation.truncate();
+    let alpha = (arrow_tr.rotation * Vec3::X).truncate().to_angle();
+    let speed = arrow_velocity(alpha, launch_power(charging.charge_time)).length();
+    let enemy_pos = enemy_tr.translation.truncate();
+    let Some(lead) = calculate_intercept_point(shooter, enemy_pos, enemy_vel.linvel, speed) else {
+        return;
+    };
+
+    let (Some(from), Some(to)) = (
+        world_to_overlay(enemy_pos, (camera, camera_transform), cursor_camera),
+        world_to_overlay(lead, (camera, camera_transform), cursor_camera),
+    ) else {
+        return;
+    };
+    let color = Color::srgb(0.3, 1., 1.);
+    gizmos.line_2d(from, to, color.with_alpha(0.3));
+    let corners = [Vec2::X, Vec2::Y, Vec2::NEG_X, Vec2::NEG_Y].map(|c| to + c * LEAD_MARKER_SIZE);
+    gizmos.linestrip_2d(corners.into_iter().chain([corners[0]]), color);
+}
@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use bevy_hanabi::ParticleEffect;
+use bevy_rapier2d::prelude::*;
+
+use crate::boss::WeakPoint;
+use crate::crosshair::Hitmarker;
+use crate::elite::{Shield, SHIELD_DAMAGE_TAKEN};
+use crate::element::Element;
+use crate::enemy::{Enemy, HP};
+use crate::feedback::Invulnerable;
+use crate::global::ScreenShake;
+use crate::particles::ParticleHandles;
+use crate::planets::{EffectType, Planet};
+use crate::player::{ColorId, Player, PlayerHealth};
+use crate::sfx::SFX;
+use crate::spawning::Portal;
+use crate::AppState;
+
+const DOT_TICK: f32 = 0.25;
+
+/// What dealt the damage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageSource {
+    Arrow,
+    Bash,
+    Ram,
+    DamageOverTime,
+    /// An enemy touching the player.
+    Contact,
+    EnemyProjectile,
+    Explosion,
+    /// A raider chipping away at a planet.
+    Raid,
+}
+
+/// Kind of entity that took the damage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageTarget {
+    Enemy,
+    Planet,
+    Player,
+    Portal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Poison,
+    Fire,
+}
+
+impl DamageType {
+    pub fn from_effect(effect_type: EffectType) -> Self {
+        match effect_type {
+            EffectType::Poison => DamageType::Poison,
+            EffectType::Fire => DamageType::Fire,
+            _ => DamageType::Physical,
+        }
+    }
+
+    /// Damage per second and duration of the lingering effect left by a crystal of `level`.
+    pub fn damage_over_time(&self, level: i32) -> Option<(f32, f32)> {
+        match self {
+            DamageType::Physical => None,
+            DamageType::Poison => Some((0.4 * level as f32, 4.0)),
+            DamageType::Fire => Some((0.8 * level as f32, 2.0)),
+        }
+    }
+}
+
+/// Request to hurt `target`. Anything that deals damage only sends this event,
+/// [`resolve_damage`] applies it and answers with a [`DamageDealt`].
+#[derive(Event, Clone)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub source: DamageSource,
+    /// Enemy that dealt the damage, if it came from one.
+    pub attacker: Option<Entity>,
+    pub damage_type: DamageType,
+    /// Color of the crystal behind the hit, matched against the target's [`Element`].
+    pub color: Option<ColorId>,
+    pub amount: f32,
+    pub crit: bool,
+    pub knockback: Vec2,
+    pub position: Vec2,
+}
+
+/// Damage that actually landed, after resistances.
+#[derive(Event, Clone)]
+pub struct DamageDealt {
+    pub target: Entity,
+    pub source: DamageSource,
+    pub attacker: Option<Entity>,
+    pub damage_type: DamageType,
+    pub amount: f32,
+    pub crit: bool,
+    pub position: Vec2,
+    pub killed: bool,
+    pub target_kind: DamageTarget,
+}
+
+/// Multipliers applied to incoming damage of each type.
+#[derive(Component, Clone, Copy)]
+pub struct Resistances {
+    pub physical: f32,
+    pub poison: f32,
+    pub fire: f32,
+}
+
+impl Default for Resistances {
+    fn default() -> Self {
+        Resistances { physical: 1., poison: 1., fire: 1. }
+    }
+}
+
+impl Resistances {
+    pub fn multiplier(&self, damage_type: DamageType) -> f32 {
+        match damage_type {
+            DamageType::Physical => self.physical,
+            DamageType::Poison => self.poison,
+            DamageType::Fire => self.fire,
+        }
+    }
+}
+
+/// Lingering poison or fire, ticking as regular damage events.
+#[derive(Component)]
+pub struct DamageOverTime {
+    pub damage_type: DamageType,
+    pub dps: f32,
+    pub remaining: f32,
+    tick: f32,
+}
+
+impl DamageOverTime {
+    pub fn new(damage_type: DamageType, dps: f32, duration: f32) -> Self {
+        DamageOverTime { damage_type, dps, remaining: duration, tick: 0. }
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DamageSystems {
+    Resolve,
+    /// Reactions to `DamageDealt`, the damaged entities are all still alive here.
+    React,
+    /// Removal of whatever was killed.
+    Despawn,
+}
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<DamageEvent>()
+            .add_event::<DamageDealt>()
+            .configure_sets(Update, (DamageSystems::Resolve, DamageSystems::React, DamageSystems::Despawn).chain())
+            .add_systems(Update, (
+                tick_damage_over_time.before(DamageSystems::Resolve),
+                resolve_damage.in_set(DamageSystems::Resolve),
+                damage_feedback.in_set(DamageSystems::React),
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+fn tick_damage_over_time(
+    dot_query: Query<(Entity, &mut DamageOverTime, &GlobalTransform)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut dot, transform) in dot_query {
+        dot.remaining -= time.delta_secs();
+        dot.tick += time.delta_secs();
+        if dot.tick >= DOT_TICK {
+            dot.tick -= DOT_TICK;
+            damage_events.write(DamageEvent {
+                target: entity,
+                source: DamageSource::DamageOverTime,
+                attacker: None,
+                damage_type: dot.damage_type,
+                color: None,
+                amount: dot.dps * DOT_TICK,
+                crit: false,
+                knockback: Vec2::ZERO,
+                position: transform.translation().truncate(),
+            });
+        }
+        if dot.remaining <= 0. {
+            commands.entity(entity).remove::<DamageOverTime>();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn resolve_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    weak_point_query: Query<(&WeakPoint, &ChildOf)>,
+    mut enemy_query: Query<(&mut HP, &mut Velocity, Option<&Resistances>, Option<&mut Shield>, Option<&Element>), With<Enemy>>,
+    mut planet_query: Query<(&mut Planet, Option<&Resistances>), Without<Enemy>>,
+    mut player_query: Query<(&mut PlayerHealth, &mut Velocity, Has<Invulnerable>), (With<Player>, Without<Enemy>)>,
+    mut portal_query: Query<(&mut HP, Option<&Resistances>), (With<Portal>, Without<Enemy>)>,
+    mut dealt: EventWriter<DamageDealt>,
+) {
+    // the invulnerability window only starts after this frame's hits are resolved
+    let mut player_hit = false;
+    for event in damage_events.read() {
+        let mut event = event.clone();
+        // weak points hand the hit to their owner, direct hits amplified
+        if let Ok((weak_point, child_of)) = weak_point_query.get(event.target) {
+            event.target = child_of.parent();
+            if event.source != DamageSource::DamageOverTime {
+                event.amount *= weak_point.multiplier;
+                event.crit = true;
+            }
+        }
+        let resolved = if let Ok((mut hp, mut velocity, resistances, shield, element)) = enemy_query.get_mut(event.target) {
+            // already dead this frame, waiting to be despawned
+            if hp.current <= 0. {
+                continue;
+            }
+            let mut amount = event.amount * resistances.copied().unwrap_or_default().multiplier(event.damage_type);
+            if let (Some(color), Some(element)) = (event.color, element) {
+                amount *= element.affinity(color);
+            }
+            if let Some(mut shield) = shield
+                && shield.remaining > 0.
+            {
+                shield.remaining -= amount;
+                amount *= SHIELD_DAMAGE_TAKEN;
+            }
+            hp.current -= amount;
+            velocity.linvel += event.knockback;
+            Some((amount, hp.current <= 0., DamageTarget::Enemy))
+        } else if let Ok((mut planet, resistances)) = planet_query.get_mut(event.target) {
+            if planet.hp <= 0. {
+                continue;
+            }
+            let amount = event.amount * resistances.copied().unwrap_or_default().multiplier(event.damage_type);
+            planet.hp -= amount;
+            Some((amount, planet.hp <= 0., DamageTarget::Planet))
+        } else if let Ok((mut health, mut velocity, invulnerable)) = player_query.get_mut(event.target) {
+            if invulnerable || player_hit {
+                continue;
+            }
+            player_hit = true;
+            health.current -= event.amount;
+            velocity.linvel += event.knockback;
+            Some((event.amount, health.current <= 0., DamageTarget::Player))
+        } else if let Ok((mut hp, resistances)) = portal_query.get_mut(event.target) {
+            if hp.current <= 0. {
+                continue;
+            }
+            let amount = event.amount * resistances.copied().unwrap_or_default().multiplier(event.damage_type);
+            hp.current -= amount;
+            Some((amount, hp.current <= 0., DamageTarget::Portal))
+        } else {
+            None
+        };
+
+        if let Some((amount, killed, target_kind)) = resolved {
+            dealt.write(DamageDealt {
+                target: event.target,
+                source: event.source,
+                attacker: event.attacker,
+                damage_type: event.damage_type,
+                amount,
+                crit: event.crit,
+                position: event.position,
+                killed,
+                target_kind,
+            });
+        }
+    }
+}
+
+fn damage_feedback(
+    mut dealt: EventReader<DamageDealt>,
+    mut commands: Commands,
+    mut shake: ResMut<ScreenShake>,
+    mut hitmarker: ResMut<Hitmarker>,
+    particle_handles: Res<ParticleHandles>,
+    sfx: Res<SFX>,
+) {
+    for event in dealt.read() {
+        commands.spawn((
+            ParticleEffect::new(particle_handles.enemy_damage.clone()),
+            Transform::from_translation(event.position.extend(0.)),
+        ));
+        // nothing for the player to feel when it wasn't involved
+        if matches!(event.source, DamageSource::DamageOverTime | DamageSource::Raid) {
+            continue;
+        }
+        if event.target_kind == DamageTarget::Player {
+            shake.trauma = shake.trauma.max(2.0);
+            continue;
+        }
+        shake.trauma = shake.trauma.max(1.0);
+        hitmarker.trigger(event.crit);
+        commands.spawn(AudioPlayer(sfx.hurt.clone()));
+    }
+}
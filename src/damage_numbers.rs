@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+
+use crate::damage::{DamageDealt, DamageSource, DamageSystems, DamageTarget, DamageType};
+use crate::global::{world_to_overlay, Settings};
+use crate::{AppState, CursorCamera, FirstPass};
+
+const NUMBER_LIFETIME: f32 = 0.8;
+const NUMBER_RISE: f32 = 40.0;
+const NUMBER_SIZE: f32 = 14.0;
+const CRIT_SIZE: f32 = 22.0;
+/// DoT ticks landing on the same target within this window add up into one number.
+const MERGE_WINDOW: f32 = 0.5;
+
+/// Pop-up number drawn on the crisp overlay above whatever took damage.
+#[derive(Component)]
+pub struct DamageNumber {
+    target: Entity,
+    damage_type: DamageType,
+    amount: f32,
+    world_pos: Vec2,
+    age: f32,
+    merging: bool,
+}
+
+pub struct DamageNumbersPlugin;
+
+impl Plugin for DamageNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                spawn_damage_numbers.in_set(DamageSystems::React),
+                update_damage_numbers,
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+fn damage_color(damage_type: DamageType, crit: bool) -> Color {
+    match damage_type {
+        DamageType::Physical if crit => Color::srgb(1., 0.8, 0.),
+        DamageType::Physical => Color::WHITE,
+        DamageType::Poison => Color::srgb(0.4, 1., 0.3),
+        DamageType::Fire => Color::srgb(1., 0.5, 0.1),
+    }
+}
+
+fn format_amount(amount: f32) -> String {
+    if amount.fract().abs() < 0.05 {
+        format!("{amount:.0}")
+    } else {
+        format!("{amount:.1}")
+    }
+}
+
+fn spawn_damage_numbers(
+    mut dealt: EventReader<DamageDealt>,
+    mut number_query: Query<(&mut DamageNumber, &mut Text2d)>,
+    settings: Res<Settings>,
+    assets: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    for event in dealt.read() {
+        if !settings.damage_numbers || event.amount <= 0. || event.target_kind == DamageTarget::Player {
+            continue;
+        }
+
+        if event.source == DamageSource::DamageOverTime
+            && let Some((mut number, mut text)) = number_query
+                .iter_mut()
+                .find(|(n, _)| n.merging && n.target == event.target && n.damage_type == event.damage_type)
+        {
+            number.amount += event.amount;
+            number.world_pos = event.position;
+            number.age = 0.;
+            text.0 = format_amount(number.amount);
+            continue;
+        }
+
+        commands.spawn((
+            DamageNumber {
+                target: event.target,
+                damage_type: event.damage_type,
+                amount: event.amount,
+                world_pos: event.position,
+                age: 0.,
+                merging: event.source == DamageSource::DamageOverTime,
+            },
+            Text2d::new(format_amount(event.amount)),
+            TextFont {
+                font: assets.load("Kenneymini.ttf"),
+                font_size: if event.crit { CRIT_SIZE } else { NUMBER_SIZE },
+                ..default()
+            },
+            TextColor(damage_color(event.damage_type, event.crit)),
+            // hidden until placed on the overlay
+            Transform::from_xyz(0., 0., 10.),
+            Visibility::Hidden,
+            RenderLayers::layer(1),
+        ));
+    }
+}
+
+fn update_damage_numbers(
+    mut number_query: Query<(Entity, &mut DamageNumber, &mut Transform, &mut TextColor, &mut Visibility)>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<FirstPass>>,
+    cursor_camera_q: Query<(&Camera, &GlobalTransform), With<CursorCamera>>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let (camera, camera_transform) = camera_q.single().unwrap();
+    let cursor_camera = cursor_camera_q.single().unwrap();
+
+    for (entity, mut number, mut transform, mut color, mut visibility) in &mut number_query {
+        number.age += time.delta_secs();
+        if number.age > MERGE_WINDOW {
+            number.merging = false;
+        }
+        if number.age >= NUMBER_LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let Some(pos) = world_to_overlay(number.world_pos, (camera, camera_transform), cursor_camera) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let t = number.age / NUMBER_LIFETIME;
+        transform.translation = (pos + Vec2::Y * NUMBER_RISE * t).extend(10.);
+        color.0.set_alpha(1. - t * t);
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::enemy::Enemy;
+use crate::global::adjusted_glow;
+use crate::player::ColorId;
+use crate::world::Palette;
+use crate::{AppState, GLOW_FACTOR};
+
+/// Damage multiplier of a crystal matching the enemy's color...
+const WEAKNESS: f32 = 1.75;
+/// ...and of one at least `AFFINITY_RANGE` away from it.
+const RESISTED: f32 = 0.5;
+const AFFINITY_RANGE: f32 = 0.5;
+/// Mix of the enemy's color towards its element, enough to tell them apart
+/// while still reading as enemies.
+const ELEMENT_TINT: f32 = 0.6;
+
+/// Color an enemy is weak to, crystals far from it do less damage.
+#[derive(Component)]
+pub struct Element(pub ColorId);
+
+impl Element {
+    /// Damage multiplier of a hit carrying a crystal of `color`.
+    pub fn affinity(&self, color: ColorId) -> f32 {
+        let closeness = 1. - (self.0.distance(&color) / AFFINITY_RANGE).min(1.);
+        RESISTED + (WEAKNESS - RESISTED) * closeness
+    }
+}
+
+pub struct ElementPlugin;
+
+impl Plugin for ElementPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, assign_elements.run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Gives new enemies one of the planets' colors and tints them with it.
+fn assign_elements(
+    enemy_query: Query<(Entity, &MeshMaterial2d<ColorMaterial>), Added<Enemy>>,
+    palette: Res<Palette>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    let mut rng = rand::rng();
+    for (entity, material) in enemy_query {
+        let Some(color) = palette.0.choose(&mut rng) else {
+            return;
+        };
+        commands.entity(entity).insert(Element(*color));
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.color = material.color.mix(&adjusted_glow(color.to_bevy(), GLOW_FACTOR), ELEMENT_TINT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_crystals_hit_hardest() {
+        let element = Element(ColorId::new(200, 40, 40));
+        assert_eq!(element.affinity(ColorId::new(200, 40, 40)), WEAKNESS);
+        let near = element.affinity(ColorId::new(180, 60, 40));
+        assert!(near < WEAKNESS && near > RESISTED);
+    }
+
+    #[test]
+    fn distant_crystals_are_resisted() {
+        let element = Element(ColorId::new(0, 0, 0));
+        assert_eq!(element.affinity(ColorId::new(255, 255, 255)), RESISTED);
+        let edge = (AFFINITY_RANGE * 255.).ceil() as u8;
+        assert_eq!(element.affinity(ColorId::new(edge, edge, edge)), RESISTED);
+    }
+}
@@ -0,0 +1,291 @@
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_hanabi::ParticleEffect;
+use rand::prelude::*;
+
+use crate::damage::{DamageDealt, DamageEvent, DamageSource, DamageSystems, DamageTarget, DamageType};
+use crate::enemy::{Behaviour, Enemy, HP};
+use crate::global::{adjusted_glow, ScreenShake};
+use crate::particles::ParticleHandles;
+use crate::planets::{Effect, EffectType};
+use crate::player::{ColorId, Crystal, Inventory, Player};
+use crate::xp::spawn_orbs;
+use crate::{AppState, GLOW_FACTOR};
+
+const BASE_ELITE_CHANCE: f32 = 0.05;
+const ELITE_CHANCE_PER_DIFFICULTY: f32 = 0.03;
+const MAX_ELITE_CHANCE: f32 = 0.3;
+/// Chance of rolling each modifier after the first.
+const EXTRA_MODIFIER_CHANCE: f32 = 0.3;
+const MAX_MODIFIERS: usize = 3;
+/// Shield strength as a share of the enemy's max HP.
+const SHIELD_SHARE: f32 = 0.75;
+/// Share of the damage that gets through an intact shield.
+pub const SHIELD_DAMAGE_TAKEN: f32 = 0.25;
+const FAST_MULTIPLIER: f32 = 1.6;
+/// Share of max HP regenerated per second.
+const REGENERATION: f32 = 0.06;
+const EXPLOSION_RADIUS: f32 = 120.0;
+const EXPLOSION_DAMAGE: f32 = 2.0;
+const EXPLOSION_KNOCKBACK: f32 = 300.0;
+/// HP a vampiric elite gains per point of damage dealt to the player.
+const VAMPIRIC_HEAL: f32 = 3.0;
+const OUTLINE_WIDTH: f32 = 3.0;
+/// Extra XP per modifier, as a share of the enemy's regular drop.
+const XP_BONUS_PER_MODIFIER: f64 = 1.0;
+const BASE_CRYSTAL_CHANCE: f32 = 0.35;
+const CRYSTAL_CHANCE_PER_MODIFIER: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EliteModifier {
+    Shielded,
+    Fast,
+    Regenerating,
+    Volatile,
+    Vampiric,
+}
+
+impl EliteModifier {
+    pub const ALL: [EliteModifier; 5] = [
+        EliteModifier::Shielded,
+        EliteModifier::Fast,
+        EliteModifier::Regenerating,
+        EliteModifier::Volatile,
+        EliteModifier::Vampiric,
+    ];
+
+    fn color(self) -> Color {
+        match self {
+            EliteModifier::Shielded => Color::srgb(0.3, 0.8, 1.),
+            EliteModifier::Fast => Color::srgb(1., 0.95, 0.2),
+            EliteModifier::Regenerating => Color::srgb(0.3, 1., 0.4),
+            EliteModifier::Volatile => Color::srgb(1., 0.45, 0.05),
+            EliteModifier::Vampiric => Color::srgb(0.7, 0.2, 1.),
+        }
+    }
+
+    /// Vampirism is wasted on enemies that die when they touch the player.
+    fn suits(self, behaviour: Behaviour) -> bool {
+        self != EliteModifier::Vampiric || !behaviour.dies_on_contact()
+    }
+}
+
+/// Marks an enemy as elite, on top of its regular `Enemy` stats.
+#[derive(Component)]
+pub struct Elite(pub Vec<EliteModifier>);
+
+impl Elite {
+    pub fn has(&self, modifier: EliteModifier) -> bool {
+        self.0.contains(&modifier)
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.has(EliteModifier::Fast) { FAST_MULTIPLIER } else { 1. }
+    }
+}
+
+/// Damage a shielded elite can soak before taking full hits.
+#[derive(Component)]
+pub struct Shield {
+    pub remaining: f32,
+}
+
+#[derive(Component)]
+struct EliteOutline(EliteModifier);
+
+pub struct ElitePlugin;
+
+impl Plugin for ElitePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                regenerate_elites,
+                fade_broken_shields,
+                (volatile_explosions, vampiric_healing, elite_loot).in_set(DamageSystems::React),
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Rolls whether a fresh enemy spawns as an elite, returning its modifiers.
+pub fn roll_modifiers(rng: &mut impl Rng, enemy: &Enemy, difficulty: f32) -> Vec<EliteModifier> {
+    let chance = (BASE_ELITE_CHANCE + ELITE_CHANCE_PER_DIFFICULTY * difficulty).min(MAX_ELITE_CHANCE);
+    if !rng.random_bool(chance as f64) {
+        return Vec::new();
+    }
+    let mut count = 1;
+    while count < MAX_MODIFIERS && rng.random_bool(EXTRA_MODIFIER_CHANCE as f64) {
+        count += 1;
+    }
+    let suitable: Vec<_> = EliteModifier::ALL.into_iter().filter(|m| m.suits(enemy.behaviour())).collect();
+    suitable.choose_multiple(rng, count).copied().collect()
+}
+
+/// Turns an already spawned enemy into an elite with a colored outline per modifier.
+pub fn make_elite(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    entity: Entity,
+    enemy: &Enemy,
+    modifiers: Vec<EliteModifier>,
+) {
+    info!("Spawning elite {}-gon with {:?}.", enemy.sides, modifiers);
+    let mut entity_commands = commands.entity(entity);
+    if modifiers.contains(&EliteModifier::Shielded) {
+        entity_commands.insert(Shield { remaining: enemy.max_hp() * SHIELD_SHARE });
+    }
+    entity_commands.with_children(|parent| {
+        for (i, modifier) in modifiers.iter().enumerate() {
+            let radius = enemy.radius() + OUTLINE_WIDTH * (i + 1) as f32;
+            parent.spawn((
+                EliteOutline(*modifier),
+                Mesh2d(meshes.add(RegularPolygon::new(radius, enemy.sides as u32))),
+                MeshMaterial2d(materials.add(ColorMaterial::from_color(adjusted_glow(modifier.color(), GLOW_FACTOR / 2.)))),
+                // stacked behind the enemy, outermost last
+                Transform::from_xyz(0., 0., -0.1 - 0.01 * i as f32),
+                RenderLayers::layer(0),
+            ));
+        }
+    });
+    entity_commands.insert(Elite(modifiers));
+}
+
+fn regenerate_elites(
+    elite_query: Query<(&Elite, &mut HP)>,
+    time: Res<Time>,
+) {
+    for (elite, mut hp) in elite_query {
+        if elite.has(EliteModifier::Regenerating) && hp.current > 0. {
+            hp.current = (hp.current + hp.max * REGENERATION * time.delta_secs()).min(hp.max);
+        }
+    }
+}
+
+fn fade_broken_shields(
+    outline_query: Query<(Entity, &EliteOutline, &ChildOf)>,
+    shield_query: Query<(&Shield, &Transform)>,
+    mut commands: Commands,
+    particle_handles: Res<ParticleHandles>,
+) {
+    for (entity, outline, child_of) in outline_query {
+        if outline.0 != EliteModifier::Shielded {
+            continue;
+        }
+        if let Ok((shield, transform)) = shield_query.get(child_of.parent())
+            && shield.remaining <= 0.
+        {
+            commands.entity(entity).despawn();
+            commands.entity(child_of.parent()).remove::<Shield>();
+            commands.spawn((
+                ParticleEffect::new(particle_handles.bash_swing.clone()),
+                Transform::from_translation(transform.translation),
+            ));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn volatile_explosions(
+    mut dealt: EventReader<DamageDealt>,
+    elite_query: Query<(&Elite, &Transform)>,
+    target_query: Query<(Entity, &Transform), Or<(With<Enemy>, With<Player>)>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut commands: Commands,
+    mut shake: ResMut<ScreenShake>,
+    particle_handles: Res<ParticleHandles>,
+) {
+    for event in dealt.read() {
+        if !event.killed {
+            continue;
+        }
+        let Ok((elite, transform)) = elite_query.get(event.target) else {
+            continue;
+        };
+        if !elite.has(EliteModifier::Volatile) {
+            continue;
+        }
+        let center = transform.translation.truncate();
+        shake.trauma = shake.trauma.max(4.0);
+        commands.spawn((
+            ParticleEffect::new(particle_handles.crit_flash.clone()),
+            Transform::from_translation(transform.translation),
+        ));
+        for (entity, target_tr) in &target_query {
+            let offset = target_tr.translation.truncate() - center;
+            if entity == event.target || offset.length() > EXPLOSION_RADIUS {
+                continue;
+            }
+            damage_events.write(DamageEvent {
+                target: entity,
+                source: DamageSource::Explosion,
+                attacker: Some(event.target),
+                damage_type: DamageType::Fire,
+                color: None,
+                amount: EXPLOSION_DAMAGE,
+                crit: false,
+                knockback: offset.normalize_or_zero() * EXPLOSION_KNOCKBACK,
+                position: target_tr.translation.truncate(),
+            });
+        }
+    }
+}
+
+fn vampiric_healing(
+    mut dealt: EventReader<DamageDealt>,
+    mut elite_query: Query<(&Elite, &mut HP)>,
+) {
+    for event in dealt.read() {
+        if event.target_kind != DamageTarget::Player {
+            continue;
+        }
+        if let Some(attacker) = event.attacker
+            && let Ok((elite, mut hp)) = elite_query.get_mut(attacker)
+            && elite.has(EliteModifier::Vampiric)
+        {
+            hp.current = (hp.current + event.amount * VAMPIRIC_HEAL).min(hp.max);
+        }
+    }
+}
+
+/// Extra XP and a chance at a crystal for every elite killed.
+fn elite_loot(
+    mut dealt: EventReader<DamageDealt>,
+    elite_query: Query<(&Elite, &Enemy, &Transform)>,
+    mut inventory: ResMut<Inventory>,
+    mut commands: Commands,
+    particle_handles: Res<ParticleHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut rng = rand::rng();
+    for event in dealt.read() {
+        if !event.killed {
+            continue;
+        }
+        let Ok((elite, enemy, transform)) = elite_query.get(event.target) else {
+            continue;
+        };
+        let modifiers = elite.0.len();
+        spawn_orbs(
+            &mut commands,
+            enemy.xp() * XP_BONUS_PER_MODIFIER * modifiers as f64,
+            transform.translation,
+            &particle_handles,
+            &mut meshes,
+            &mut materials,
+        );
+
+        let crystal_chance = BASE_CRYSTAL_CHANCE + CRYSTAL_CHANCE_PER_MODIFIER * (modifiers - 1) as f32;
+        if rng.random_bool(crystal_chance.min(1.) as f64) {
+            let color = elite.0.choose(&mut rng).unwrap().color().to_srgba();
+            let crystal = Crystal {
+                color: ColorId::new((color.red * 255.) as u8, (color.green * 255.) as u8, (color.blue * 255.) as u8),
+                effect: Effect { effect_type: *EffectType::ALL.choose(&mut rng).unwrap(), level: modifiers as i32 },
+                phase: rng.random(),
+                resonance: rng.random(),
+            };
+            info!("Elite dropped a {:?} crystal.", crystal.effect.effect_type);
+            inventory.crystals.push(crystal);
+        }
+    }
+}
@@ -1,18 +1,115 @@
+use std::f32::consts::{PI, TAU};
 use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
 use bevy_hanabi::ParticleEffect;
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
 
 
-use crate::{arrow::Arrow, global::{CircleCollider, ScreenShake}, particles::ParticleHandles, player::{Player, PlayerHealth}, ui::LastDamageTime, world::EnemiesCounter, xp::spawn_orbs, AppState};
-use crate::sfx::SFX;
+use crate::{behaviour::Brain, boss::Boss, damage::{DamageDealt, DamageEvent, DamageSource, DamageSystems, DamageType}, feedback::Invulnerable, global::{regular_polygon_vertices, CircleCollider, ScreenShake}, particles::ParticleHandles, physics::CollisionLayer, player::Player, projectile::Shooter, upgrades::Upgrades, world::EnemiesCounter, xp::spawn_orbs, AppState, FirstPass, ENEMY_COLOR, GLOW_FACTOR};
 
+pub const MIN_SIDES: i32 = 3;
+pub const MAX_SIDES: i32 = 12;
 const ENEMY_SPEED: f32 = 50.0;
 const ENEMY_DAMAGE: i32 = 1;
+const BASE_HP: f32 = 4.0;
+const HP_PER_SIDE: f32 = 3.0;
+const BASE_RADIUS: f32 = 30.0;
+const RADIUS_PER_SIDE: f32 = 2.5;
+/// Speed multiplier for every side above a triangle.
+const SPEED_PER_SIDE: f32 = 0.92;
+const BASE_XP: f64 = 5.0;
+const XP_PER_SIDE: f64 = 2.5;
+/// Every this many extra sides add one point of contact damage.
+const SIDES_PER_DAMAGE: i32 = 3;
+const JUGGERNAUT_VOLLEY: u32 = 5;
+/// Size, HP and XP multipliers of a fragment against a spawned enemy with as many sides.
+const SPLIT_SIZE: f32 = 0.8;
+const SPLIT_HP: f32 = 0.5;
+const SPLIT_XP: f64 = 0.5;
+const SPLIT_BURST: f32 = 250.0;
+/// Closing speed above which touching an enemy rams it instead of hurting the player.
+const RAM_SPEED: f32 = 280.0;
+const RAM_DAMAGE_PER_SPEED: f32 = 0.008;
+/// Share of the closing speed the player loses on a ram.
+const RAM_RECOIL: f32 = 0.5;
+const RAM_IMMUNITY: f32 = 0.4;
+const HULL_SPEED_FACTOR: f32 = 0.85;
+const HULL_DAMAGE_BONUS: f32 = 0.5;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 #[require(Velocity, Mesh2d, MeshMaterial2d<ColorMaterial>, HP, CircleCollider)]
 pub struct Enemy {
     pub sides: i32,
+    /// Piece of a bigger enemy that died, rather than a spawned one.
+    pub split: bool,
+}
+
+/// What an enemy does, picked by its side count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behaviour {
+    /// Triangles run straight at the player.
+    Chaser,
+    /// Squares head for where the player is going to be.
+    Interceptor,
+    /// 5-6 sides keep their distance and shoot.
+    Gunner,
+    /// 7-9 sides close in and dash every few seconds.
+    Charger,
+    /// 10-12 sides are slow, fire volleys and survive ramming the player.
+    Juggernaut,
+}
+
+impl Behaviour {
+    /// Whether the enemy is spent when it touches the player.
+    pub fn dies_on_contact(self) -> bool {
+        matches!(self, Behaviour::Chaser | Behaviour::Interceptor | Behaviour::Charger)
+    }
+}
+
+impl Enemy {
+    pub fn new(sides: i32) -> Self {
+        Enemy { sides: sides.clamp(MIN_SIDES, MAX_SIDES), split: false }
+    }
+
+    /// Smaller polygon left behind when this one dies, if it splits at all.
+    pub fn fragment(&self) -> Option<Enemy> {
+        (self.sides > MIN_SIDES).then(|| Enemy { sides: self.sides - 1, split: true })
+    }
+
+    pub fn behaviour(&self) -> Behaviour {
+        match self.sides {
+            ..=3 => Behaviour::Chaser,
+            4 => Behaviour::Interceptor,
+            5..=6 => Behaviour::Gunner,
+            7..=9 => Behaviour::Charger,
+            _ => Behaviour::Juggernaut,
+        }
+    }
+
+    fn extra_sides(&self) -> i32 {
+        (self.sides - MIN_SIDES).max(0)
+    }
+
+    pub fn max_hp(&self) -> f32 {
+        (BASE_HP + HP_PER_SIDE * self.extra_sides() as f32) * if self.split { SPLIT_HP } else { 1. }
+    }
+
+    pub fn radius(&self) -> f32 {
+        (BASE_RADIUS + RADIUS_PER_SIDE * self.extra_sides() as f32) * if self.split { SPLIT_SIZE } else { 1. }
+    }
+
+    pub fn speed(&self) -> f32 {
+        ENEMY_SPEED * SPEED_PER_SIDE.powi(self.extra_sides())
+    }
+
+    pub fn xp(&self) -> f64 {
+        (BASE_XP + XP_PER_SIDE * self.extra_sides() as f64) * if self.split { SPLIT_XP } else { 1. }
+    }
+
+    pub fn contact_damage(&self) -> f32 {
+        (ENEMY_DAMAGE + self.extra_sides() / SIDES_PER_DAMAGE) as f32
+    }
 }
 
 
@@ -25,6 +122,10 @@ pub struct HP {
 #[derive(Component, Default)]
 pub struct HealthBar;
 
+/// Enemy that was just rammed and can neither be rammed again nor hurt the player yet.
+#[derive(Component)]
+struct Rammed(f32);
+
 #[derive(Component)]
 pub struct HealthBarOwner(pub Entity);
 
@@ -33,7 +134,7 @@ pub struct EnemyPlugin;
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Update, (update_health_bars, update_health_bar_position, handle_ai, handle_collision, collision_events_system).run_if(in_state(AppState::InGame)));
+            .add_systems(Update, (update_health_bars, update_health_bar_position, handle_collision.before(DamageSystems::Resolve), handle_enemy_deaths.in_set(DamageSystems::Despawn), despawn_crashed_enemies.in_set(DamageSystems::Despawn)).run_if(in_state(AppState::InGame)));
     }
 }
 
@@ -43,29 +144,29 @@ fn update_health_bars(
 ) {
     for (mut bar_transform, owner) in &mut bar_query {
         if let Ok((hp, _)) = health_query.get(owner.0) {
-            let health_percent: f32 = hp.current.max(0.) as f32 / hp.max.max(1.) as f32;
+            let health_percent: f32 = hp.current.max(0.) / hp.max;
             bar_transform.scale.x = health_percent.clamp(0.0, 1.0);
         }
     }
 }
 
 fn update_health_bar_position(
-    enemy_query: Query<&GlobalTransform, With<Enemy>>,
+    enemy_query: Query<(&GlobalTransform, &CircleCollider), With<Enemy>>,
     mut bar_query: Query<(&mut Transform, &HealthBarOwner), With<HealthBar>>,
 ) {
     for (mut bar_transform, owner) in &mut bar_query {
-        if let Ok(enemy_transform) = enemy_query.get(owner.0) {
+        if let Ok((enemy_transform, collider)) = enemy_query.get(owner.0) {
             let pos = enemy_transform.translation();
-            bar_transform.translation = Vec3::new(pos.x, pos.y + 40.0, pos.z + 0.1);
+            bar_transform.translation = Vec3::new(pos.x, pos.y + collider.0 + 10.0, pos.z + 0.1);
             bar_transform.rotation = Quat::IDENTITY;
         }
     }
 }
 
-fn collision_events_system(
-    mut collision_events: EventReader<CollisionEvent>,
-    mut enemy_query: Query<(Entity, &mut HP, &mut Velocity, &Transform), With<Enemy>>,
-    mut arrow_query: Query<(Entity, &Velocity, &Arrow, &Transform), Without<Enemy>>,
+#[allow(clippy::too_many_arguments)]
+fn handle_enemy_deaths(
+    mut dealt: EventReader<DamageDealt>,
+    enemy_query: Query<(&Transform, &Enemy, Has<Boss>)>,
     health_bar_query: Query<(Entity, &HealthBarOwner), With<HealthBar>>,
     mut commands: Commands,
     mut shake: ResMut<ScreenShake>,
@@ -73,129 +174,157 @@ fn collision_events_system(
     mut enemies: ResMut<EnemiesCounter>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    sfx: Res<SFX>,
 ) {
-    for collision in collision_events.read() {
-        match collision {
-            CollisionEvent::Started(e1, e2, _) => {
-                if let Ok(mut enemy) = enemy_query.get_mut(e1.entity()) {
-                    if let Ok(mut arrow) = arrow_query.get_mut(e2.entity()) {
-                        handle_enemy_damage(
-                            &mut commands,
-                            &mut enemy,
-                            &mut arrow,
-                            health_bar_query,
-                            &mut enemies,
-                            &mut shake,
-                            &particle_handles,
-                            &mut meshes,
-                            &mut materials,
-                            &sfx,
-                        );
-                    }
-                }
-                if let Ok(mut enemy) = enemy_query.get_mut(e2.entity()) {
-                    if let Ok(mut arrow) = arrow_query.get_mut(e1.entity()) {
-                        handle_enemy_damage(
-                            &mut commands,
-                            &mut enemy,
-                            &mut arrow,
-                            health_bar_query,
-                            &mut enemies,
-                            &mut shake,
-                            &particle_handles,
-                            &mut meshes,
-                            &mut materials,
-                            &sfx,
-                        );
-                    }
-                }
+    let mut rng = rand::rng();
+    for event in dealt.read() {
+        if !event.killed {
+            continue;
+        }
+        if let Ok((transform, enemy, boss)) = enemy_query.get(event.target) {
+            kill_enemy(
+                &mut commands,
+                event.target,
+                transform.translation,
+                health_bar_query,
+                &mut enemies,
+                &mut shake,
+                &particle_handles,
+            );
+            // bosses drop their own loot and leave their minions behind instead
+            if boss {
+                continue;
+            }
+            spawn_orbs(
+                &mut commands,
+                enemy.xp(),
+                transform.translation,
+                &particle_handles,
+                &mut meshes,
+                &mut materials,
+            );
+            let Some(fragment) = enemy.fragment() else {
+                continue;
+            };
+            let count = rng.random_range(2..=3);
+            let center = transform.translation.truncate();
+            for i in 0..count {
+                let angle = TAU * i as f32 / count as f32 + rng.random_range(0.0..TAU);
+                let position = center + Vec2::from_angle(angle) * fragment.radius() * 0.5;
+                // pieces fly away from where the killing blow landed
+                let away = (position - event.position).normalize_or(Vec2::from_angle(angle));
+                let child = spawn_enemy(&mut commands, &mut meshes, &mut materials, &mut enemies, fragment, position);
+                commands.entity(child).insert(Velocity {
+                    linvel: away * SPLIT_BURST,
+                    angvel: 2.0 * PI / 3.0,
+                });
             }
-            _ => ()
         }
     }
 }
 
-fn handle_enemy_damage(
-    mut commands: &mut Commands,
-    enemy: &mut (Entity, Mut<HP>, Mut<Velocity>, &Transform),
-    arrow: &(Entity, &Velocity, &Arrow, &Transform),
+/// Removes a defeated enemy with its health bar.
+pub fn kill_enemy(
+    commands: &mut Commands,
+    entity: Entity,
+    translation: Vec3,
     health_bar_query: Query<(Entity, &HealthBarOwner), With<HealthBar>>,
     enemies: &mut ResMut<EnemiesCounter>,
     shake: &mut ResMut<ScreenShake>,
     particle_handles: &Res<ParticleHandles>,
-    mut meshes: &mut ResMut<Assets<Mesh>>,
-    mut materials: &mut ResMut<Assets<ColorMaterial>>,
-    sfx: &Res<SFX>,
 ) {
-    enemy.1.current -= arrow.2.damage;
-    commands.entity(arrow.0).despawn();
-    shake.trauma = 1.0;
-    commands.spawn((
-        ParticleEffect::new(particle_handles.enemy_damage.clone()),
-        Transform::from_translation(arrow.3.translation)
-    ));
-    commands.spawn(AudioPlayer(sfx.hurt.clone()));
-    enemy.2.linvel.x += arrow.1.linvel.x;
-    enemy.2.linvel.y += arrow.1.linvel.y;
-
-    if enemy.1.current <= 0. {
-        for (bar_ent, owner) in &health_bar_query {
-            if owner.0 == enemy.0 {
-                commands.entity(bar_ent).despawn();
-            }
+    for (bar_ent, owner) in &health_bar_query {
+        if owner.0 == entity {
+            commands.entity(bar_ent).despawn();
         }
+    }
 
-        commands.entity(enemy.0).despawn();
-        shake.trauma = 4.0;
-        enemies.0 -= 1;
-
-        commands.spawn((
-            ParticleEffect::new(particle_handles.enemy_death.clone()),
-            Transform::from_translation(enemy.3.translation),
-        ));
+    commands.entity(entity).despawn();
+    shake.trauma = 4.0;
+    enemies.0 -= 1;
 
-        spawn_orbs(
-            &mut commands,
-            5.,
-            enemy.3.translation,
-            &particle_handles,
-            &mut meshes,
-            &mut materials,
-        );
-    }
+    commands.spawn((
+        ParticleEffect::new(particle_handles.enemy_death.clone()),
+        Transform::from_translation(translation),
+    ));
 }
 
 
 
 
-fn handle_ai(
-    enemy_query: Query<(&Transform, &mut Velocity, &Enemy), Without<Player>>,
-    player_query: Query<(&Transform, &Velocity), (With<Player>, Without<Enemy>)>,
-) {
-    let player = player_query.single().unwrap();
-    for (enemy_tr, mut vel, enemy) in enemy_query {
-        match enemy.sides {
-            3 => {
-                let player_tr = player.0;
-
-                let d = (player_tr.translation - enemy_tr.translation).truncate().normalize();
-                vel.linvel = vel.linvel.lerp(d * ENEMY_SPEED, 0.1);
-            }
-            4 => {
-                let player_tr = player.0;
-                if let Some(d) = calculate_intercept_direction(enemy_tr.translation.truncate(), player_tr.translation.truncate(), Vec2::new(player.1.linvel.x, player.1.linvel.y), ENEMY_SPEED) {
-                    vel.linvel = vel.linvel.lerp(d * ENEMY_SPEED, 0.1);
-                } else {
-                    vel.linvel = vel.linvel.lerp((-enemy_tr.translation + player_tr.translation).truncate().normalize() * ENEMY_SPEED, 0.1);
-                }
-            }
-            _ => ()
+/// Spawns `enemy` with stats scaled by its shape, and its health bar.
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    enemies: &mut ResMut<EnemiesCounter>,
+    enemy: Enemy,
+    position: Vec2,
+) -> Entity {
+    let radius = enemy.radius();
+    let hp = enemy.max_hp();
+    let behaviour = enemy.behaviour();
+    let sides = enemy.sides;
+
+    let id = commands.spawn((
+        enemy,
+        Mesh2d(meshes.add(RegularPolygon::new(radius, sides as u32))),
+        MeshMaterial2d(materials.add(ColorMaterial::from_color(ENEMY_COLOR))),
+        HP {
+            current: hp,
+            max: hp,
+        },
+        CircleCollider(radius),
+        Transform::from_translation(position.extend(0.)),
+        GlobalTransform::default(),
+        Collider::convex_hull(&regular_polygon_vertices(radius, sides as usize).iter().map(|e| {Vect::from(*e)}).collect::<Vec<Vect>>()).unwrap(),
+        RigidBody::Dynamic,
+        CollisionLayer::Enemy.bundle(),
+        Velocity::angular(2.0 * PI / 3.0),
+        Restitution::coefficient(1.0),
+        RenderLayers::layer(0),
+        FirstPass,
+    )).id();
+    commands.entity(id).insert(Brain::new(behaviour.states()));
+    match behaviour {
+        Behaviour::Gunner => {
+            commands.entity(id).insert(Shooter::default());
         }
+        Behaviour::Juggernaut => {
+            commands.entity(id).insert(Shooter::volley(JUGGERNAUT_VOLLEY));
+        }
+        _ => (),
     }
+
+    spawn_health_bar(commands, meshes, materials, id, position + Vec2::new(0., radius + 10.));
+    enemies.0 += 1;
+    id
 }
 
-fn calculate_intercept_direction(
+/// World space health bar of `owner`, kept above it by [`update_health_bar_position`] if it's an enemy.
+pub fn spawn_health_bar(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    owner: Entity,
+    position: Vec2,
+) {
+    commands.spawn((
+        Mesh2d(meshes.add(Rectangle::new(75.0, 15.0))),
+        MeshMaterial2d(materials.add(ColorMaterial::from_color(Color::linear_rgb(GLOW_FACTOR, 0.0, 0.0)))),
+        Transform {
+            translation: position.extend(0.1),
+            ..default()
+        },
+        GlobalTransform::default(),
+        HealthBar,
+        HealthBarOwner(owner),
+        RenderLayers::layer(0),
+        FirstPass,
+    ));
+}
+
+/// Point where a projectile fired now at `projectile_speed` meets a target moving at `target_vel`.
+pub fn calculate_intercept_point(
     shooter_pos: Vec2,
     target_pos: Vec2,
     target_vel: Vec2,
@@ -220,43 +349,146 @@ fn calculate_intercept_direction(
         return None;
     };
 
-    let aim = target_pos + target_vel * t;
-    Some((aim - shooter_pos).normalize())
+    Some(target_pos + target_vel * t)
 }
 
+#[allow(clippy::type_complexity)]
 fn handle_collision(
-    mut player_query: Query<(&mut PlayerHealth, &CircleCollider, &Transform)>,
-    enemy_query: Query<(&CircleCollider, &Transform, &Enemy, Entity)>,
-    health_bar_query: Query<(Entity, &HealthBarOwner), With<HealthBar>>,
+    mut player_query: Query<(Entity, &mut Velocity, &CircleCollider, &Transform, Has<Invulnerable>), With<Player>>,
+    enemy_query: Query<(&CircleCollider, &Transform, &Velocity, &Enemy, Entity, Option<&mut Rammed>), Without<Player>>,
     mut commands: Commands,
-    mut counter: ResMut<EnemiesCounter>,
-    mut last_damage: ResMut<LastDamageTime>,
     time: Res<Time>,
-    mut shake: ResMut<ScreenShake>,
     particle_handles: Res<ParticleHandles>,
+    mut damage_events: EventWriter<DamageEvent>,
+    upgrades: Res<Upgrades>,
+) {
+    let (player, mut player_vel, player_collider, player_tr, mut invulnerable) = player_query.single_mut().unwrap();
+    let ram_speed = RAM_SPEED * HULL_SPEED_FACTOR.powi(upgrades.hull as i32);
+    let ram_damage = RAM_DAMAGE_PER_SPEED * (1. + HULL_DAMAGE_BONUS * upgrades.hull as f32);
+
+    for (enemy_collider, enemy_tr, enemy_vel, enemy, entity, rammed) in enemy_query {
+        if let Some(mut rammed) = rammed {
+            rammed.0 -= time.delta_secs();
+            if rammed.0 <= 0. {
+                commands.entity(entity).remove::<Rammed>();
+            }
+            continue;
+        }
+        let offset = enemy_tr.translation.truncate() - player_tr.translation.truncate();
+        if offset.length() >= enemy_collider.0 + player_collider.0 {
+            continue;
+        }
+
+        let dir = offset.normalize_or_zero();
+        let closing = (player_vel.linvel - enemy_vel.linvel).dot(dir);
+        if closing >= ram_speed {
+            damage_events.write(DamageEvent {
+                target: entity,
+                source: DamageSource::Ram,
+                attacker: None,
+                damage_type: DamageType::Physical,
+                color: None,
+                amount: closing * ram_damage,
+                crit: false,
+                knockback: dir * closing,
+                position: enemy_tr.translation.truncate(),
+            });
+            player_vel.linvel -= dir * closing * RAM_RECOIL;
+            commands.entity(entity).insert(Rammed(RAM_IMMUNITY));
+            continue;
+        }
+
+        if invulnerable {
+            continue;
+        }
+        invulnerable = true;
+        damage_events.write(DamageEvent {
+            target: player,
+            source: DamageSource::Contact,
+            attacker: Some(entity),
+            damage_type: DamageType::Physical,
+            color: None,
+            amount: enemy.contact_damage(),
+            crit: false,
+            knockback: Vec2::ZERO,
+            position: player_tr.translation.truncate(),
+        });
+        commands.spawn((
+            ParticleEffect::new(particle_handles.enemy_damage.clone()),
+            Transform::from_translation(enemy_tr.translation),
+        ));
+    }
+}
+
+/// Removes enemies that die on contact, once their hit on the player actually landed.
+fn despawn_crashed_enemies(
+    mut dealt: EventReader<DamageDealt>,
+    enemy_query: Query<(&Enemy, &HP)>,
+    health_bar_query: Query<(Entity, &HealthBarOwner), With<HealthBar>>,
+    mut commands: Commands,
+    mut counter: ResMut<EnemiesCounter>,
 ) {
-    let (mut health, player_collider, player_tr) = player_query.single_mut().unwrap();
-    for (enemy_collider, enemy_tr, enemy, entity) in enemy_query {
-        if enemy_tr.translation.truncate().distance(player_tr.translation.truncate()) < enemy_collider.0 + player_collider.0 {
-            match enemy.sides {
-                3..=4 => {
-                    health.current -= ENEMY_DAMAGE as f32;
-                    commands.entity(entity).despawn();
-                    for (health_bar_ent, owner) in health_bar_query {
-                        if owner.0 == entity {
-                            commands.entity(health_bar_ent).despawn();
-                        }
-                    }
-                    counter.0 -= 1;
-                    last_damage.0 = time.elapsed_secs();
-                    shake.trauma = 2.0;
-                    commands.spawn((
-                        ParticleEffect::new(particle_handles.enemy_damage.clone()),
-                        Transform::from_translation(enemy_tr.translation),
-                    ));
-                }
-                _ => ()
+    for event in dealt.read() {
+        if event.source != DamageSource::Contact {
+            continue;
+        }
+        let Some(entity) = event.attacker else {
+            continue;
+        };
+        // killed by something else this frame, handle_enemy_deaths takes care of it
+        let Ok((enemy, hp)) = enemy_query.get(entity) else {
+            continue;
+        };
+        if !enemy.behaviour().dies_on_contact() || hp.current <= 0. {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        for (health_bar_ent, owner) in health_bar_query {
+            if owner.0 == entity {
+                commands.entity(health_bar_ent).despawn();
             }
         }
+        counter.0 -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_down_to_triangles() {
+        let mut enemy = Enemy::new(MAX_SIDES);
+        let mut splits = 0;
+        while let Some(fragment) = enemy.fragment() {
+            assert_eq!(fragment.sides, enemy.sides - 1);
+            enemy = fragment;
+            splits += 1;
+        }
+        assert_eq!(enemy.sides, MIN_SIDES);
+        assert_eq!(splits, MAX_SIDES - MIN_SIDES);
+    }
+
+    #[test]
+    fn fragment_stats_follow_own_sides() {
+        let deep = Enemy::new(MIN_SIDES + 1).fragment().unwrap();
+        let shallow = Enemy::new(MAX_SIDES).fragment().unwrap().fragment().unwrap();
+        // however deep the split, a fragment is a weakened version of its own shape
+        assert_eq!(deep.max_hp(), Enemy::new(MIN_SIDES).max_hp() * SPLIT_HP);
+        assert_eq!(deep.radius(), Enemy::new(MIN_SIDES).radius() * SPLIT_SIZE);
+        assert_eq!(deep.xp(), Enemy::new(MIN_SIDES).xp() * SPLIT_XP);
+        assert_eq!(shallow.max_hp(), Enemy::new(MAX_SIDES - 2).max_hp() * SPLIT_HP);
+        assert!(deep.max_hp() >= 1.);
+    }
+
+    #[test]
+    fn fragments_are_weaker_than_their_parent() {
+        for sides in MIN_SIDES + 1..=MAX_SIDES {
+            let parent = Enemy::new(sides);
+            let fragment = parent.fragment().unwrap();
+            assert!(fragment.max_hp() < parent.max_hp());
+            assert!(fragment.radius() < parent.radius());
+            assert!(fragment.xp() < parent.xp());
+        }
     }
 }
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+
+use crate::damage::{DamageDealt, DamageSource, DamageSystems, DamageTarget};
+use crate::AppState;
+
+/// Hits at least this strong freeze the game for a moment.
+const HEAVY_HIT: f32 = 2.0;
+const HITSTOP_PER_DAMAGE: f32 = 0.015;
+const KILL_HITSTOP: f32 = 0.04;
+const MAX_HITSTOP: f32 = 0.12;
+const FLASH_DURATION: f32 = 0.12;
+/// Damage that makes the flash fully white.
+const FULL_FLASH_DAMAGE: f32 = 4.0;
+const PLAYER_HITSTOP: f32 = 0.1;
+const INVULNERABILITY: f32 = 1.0;
+
+/// Freeze frames, counted in real time while `Time<Virtual>` is paused.
+#[derive(Resource, Default)]
+pub struct HitStop {
+    remaining: f32,
+}
+
+impl HitStop {
+    pub fn trigger(&mut self, duration: f32) {
+        self.remaining = self.remaining.max(duration.min(MAX_HITSTOP));
+    }
+}
+
+/// Briefly blends the entity's `ColorMaterial` towards white.
+#[derive(Component)]
+pub struct DamageFlash {
+    strength: f32,
+    timer: f32,
+    base: Option<Color>,
+}
+
+impl DamageFlash {
+    pub fn new(strength: f32) -> Self {
+        DamageFlash { strength: strength.clamp(0., 1.), timer: FLASH_DURATION, base: None }
+    }
+
+    fn refresh(&mut self, strength: f32) {
+        self.strength = self.strength.max(strength.clamp(0., 1.));
+        self.timer = FLASH_DURATION;
+    }
+}
+
+/// Player can't be hurt while this is ticking down.
+#[derive(Component)]
+pub struct Invulnerable(pub f32);
+
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                hit_feedback.in_set(DamageSystems::React),
+                update_damage_flash,
+                tick_invulnerability,
+            ).run_if(in_state(AppState::InGame)))
+            .add_systems(Update, update_hit_stop)
+            .insert_resource(HitStop::default());
+    }
+}
+
+fn hit_feedback(
+    mut dealt: EventReader<DamageDealt>,
+    mut flash_query: Query<Option<&mut DamageFlash>>,
+    mut hit_stop: ResMut<HitStop>,
+    mut commands: Commands,
+) {
+    for event in dealt.read() {
+        if matches!(event.source, DamageSource::DamageOverTime | DamageSource::Raid) {
+            continue;
+        }
+        if event.target_kind == DamageTarget::Player {
+            hit_stop.trigger(PLAYER_HITSTOP);
+            commands.entity(event.target).insert((DamageFlash::new(1.), Invulnerable(INVULNERABILITY)));
+            continue;
+        }
+        if event.amount >= HEAVY_HIT || event.crit || event.killed {
+            let kill_bonus = if event.killed { KILL_HITSTOP } else { 0. };
+            hit_stop.trigger(event.amount * HITSTOP_PER_DAMAGE + kill_bonus);
+        }
+        if event.killed {
+            continue;
+        }
+        let strength = event.amount / FULL_FLASH_DAMAGE;
+        match flash_query.get_mut(event.target) {
+            Ok(Some(mut flash)) => flash.refresh(strength),
+            Ok(None) => {
+                commands.entity(event.target).try_insert(DamageFlash::new(strength));
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+fn update_damage_flash(
+    flash_query: Query<(Entity, &mut DamageFlash, &MeshMaterial2d<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut flash, material) in flash_query {
+        let Some(material) = materials.get_mut(&material.0) else {
+            continue;
+        };
+        let base = *flash.base.get_or_insert(material.color);
+        flash.timer -= time.delta_secs();
+        if flash.timer <= 0. {
+            material.color = base;
+            commands.entity(entity).remove::<DamageFlash>();
+            continue;
+        }
+        let t = flash.strength * flash.timer / FLASH_DURATION;
+        // brighter than white so the flash survives bloom on glowing meshes
+        let white = Color::linear_rgba(4., 4., 4., base.alpha());
+        material.color = base.mix(&white, t);
+    }
+}
+
+fn tick_invulnerability(
+    query: Query<(Entity, &mut Invulnerable)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable) in query {
+        invulnerable.0 -= time.delta_secs();
+        if invulnerable.0 <= 0. {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+fn update_hit_stop(
+    mut hit_stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+) {
+    if hit_stop.remaining > 0. {
+        virtual_time.pause();
+        hit_stop.remaining -= real_time.delta_secs();
+    } else if virtual_time.is_paused() {
+        virtual_time.unpause();
+    }
+}
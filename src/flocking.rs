@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::behaviour::{run_brains, Action, Brain};
+use crate::enemy::{calculate_intercept_point, Enemy};
+use crate::global::CircleCollider;
+use crate::morale::Morale;
+use crate::nest::Guard;
+use crate::player::Player;
+use crate::raider::Raider;
+use crate::AppState;
+
+/// Enemies further apart than this ignore each other.
+const NEIGHBOUR_RADIUS: f32 = 120.0;
+/// Extra room kept between two hulls.
+const SEPARATION_GAP: f32 = 12.0;
+/// Steering weights, as shares of the enemy's own speed.
+const SEPARATION_WEIGHT: f32 = 1.5;
+const ALIGNMENT_WEIGHT: f32 = 0.3;
+const COHESION_WEIGHT: f32 = 0.2;
+const COORDINATION_INTERVAL: f32 = 0.5;
+/// Melee enemies this close to the player take part in surrounding it.
+const COORDINATION_RANGE: f32 = 700.0;
+const MIN_GROUP: usize = 3;
+/// Members already this close to their slot angle just go straight in.
+const FLANK_THRESHOLD: f32 = 0.5;
+/// Distance from the player of the flanking waypoint.
+const FLANK_RADIUS: f32 = 160.0;
+/// Flankers stop circling and commit once this close.
+const FLANK_COMMIT: f32 = 200.0;
+
+/// Push from nearby enemies, added to the brain's own movement.
+#[derive(Component, Default)]
+pub struct Steering(pub Vec2);
+
+/// Angle around the player a group member approaches from.
+#[derive(Component)]
+pub struct Flank {
+    angle: f32,
+}
+
+impl Flank {
+    /// Direction towards the flank waypoint next to where the player is heading,
+    /// or `None` once close enough to close the pincer.
+    pub fn approach(&self, position: Vec2, player_pos: Vec2, player_vel: Vec2, speed: f32) -> Option<Vec2> {
+        if position.distance(player_pos) < FLANK_COMMIT {
+            return None;
+        }
+        let predicted = calculate_intercept_point(position, player_pos, player_vel, speed).unwrap_or(player_pos);
+        let waypoint = predicted + Vec2::from_angle(self.angle) * FLANK_RADIUS;
+        Some((waypoint - position).normalize_or_zero())
+    }
+}
+
+pub struct FlockingPlugin;
+
+impl Plugin for FlockingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                flock.before(run_brains),
+                coordinate_flanks,
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Separation, alignment and cohesion between enemies, bucketed in a grid so
+/// each one only looks at its neighbourhood.
+fn flock(
+    enemy_query: Query<(&Transform, &Velocity, &CircleCollider, &mut Steering), With<Brain>>,
+) {
+    let boids: Vec<(Vec2, Vec2, f32)> = enemy_query
+        .iter()
+        .map(|(transform, velocity, collider, _)| (transform.translation.truncate(), velocity.linvel, collider.0))
+        .collect();
+    let cell_of = |position: Vec2| (position / NEIGHBOUR_RADIUS).floor().as_ivec2();
+    let mut grid: HashMap<IVec2, Vec<usize>> = HashMap::new();
+    for (i, (position, _, _)) in boids.iter().enumerate() {
+        grid.entry(cell_of(*position)).or_default().push(i);
+    }
+
+    for (i, (_, _, _, mut steering)) in enemy_query.into_iter().enumerate() {
+        let (position, _, radius) = boids[i];
+        let cell = cell_of(position);
+        let mut separation = Vec2::ZERO;
+        let mut heading = Vec2::ZERO;
+        let mut center = Vec2::ZERO;
+        let mut count = 0;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(members) = grid.get(&(cell + IVec2::new(dx, dy))) else {
+                    continue;
+                };
+                for &j in members {
+                    let (other, other_vel, other_radius) = boids[j];
+                    let offset = position - other;
+                    let distance = offset.length();
+                    if j == i || distance > NEIGHBOUR_RADIUS {
+                        continue;
+                    }
+                    let personal_space = radius + other_radius + SEPARATION_GAP;
+                    if distance < personal_space {
+                        separation += offset.normalize_or(Vec2::X) * (1. - distance / personal_space);
+                    }
+                    heading += other_vel;
+                    center += other;
+                    count += 1;
+                }
+            }
+        }
+        if count == 0 {
+            steering.0 = Vec2::ZERO;
+            continue;
+        }
+        center /= count as f32;
+        steering.0 = separation * SEPARATION_WEIGHT
+            + heading.normalize_or_zero() * ALIGNMENT_WEIGHT
+            + (center - position).normalize_or_zero() * COHESION_WEIGHT;
+    }
+}
+
+/// Spreads the melee enemies around the player into evenly spaced approach
+/// angles, so they come from all sides instead of queueing up behind each other.
+#[allow(clippy::type_complexity)]
+fn coordinate_flanks(
+    enemy_query: Query<(Entity, &Transform, &Brain, &Morale), (With<Enemy>, Without<Guard>, Without<Raider>)>,
+    flank_query: Query<Entity, With<Flank>>,
+    player_query: Query<&Transform, With<Player>>,
+    mut commands: Commands,
+    mut cooldown: Local<f32>,
+    time: Res<Time>,
+) {
+    *cooldown -= time.delta_secs();
+    if *cooldown > 0. {
+        return;
+    }
+    *cooldown = COORDINATION_INTERVAL;
+
+    let player = player_query.single().unwrap().translation.truncate();
+    let mut group: Vec<(Entity, f32)> = enemy_query
+        .iter()
+        .filter(|(_, transform, brain, morale)| {
+            matches!(brain.action(), Action::Seek | Action::Intercept)
+                && !morale.shaken()
+                && transform.translation.truncate().distance(player) < COORDINATION_RANGE
+        })
+        .map(|(entity, transform, _, _)| (entity, (transform.translation.truncate() - player).to_angle()))
+        .collect();
+
+    for entity in &flank_query {
+        if !group.iter().any(|(member, _)| *member == entity) {
+            commands.entity(entity).try_remove::<Flank>();
+        }
+    }
+    if group.len() < MIN_GROUP {
+        for (entity, _) in group {
+            commands.entity(entity).try_remove::<Flank>();
+        }
+        return;
+    }
+
+    // keep the members' order around the player so their paths don't cross,
+    // and turn the slots to where the group already is
+    group.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let spacing = TAU / group.len() as f32;
+    let rotation = group
+        .iter()
+        .enumerate()
+        .map(|(i, (_, angle))| Vec2::from_angle(angle - spacing * i as f32))
+        .sum::<Vec2>()
+        .to_angle();
+    for (i, (entity, angle)) in group.into_iter().enumerate() {
+        let slot = rotation + spacing * i as f32;
+        let off_by = ((slot - angle + PI).rem_euclid(TAU) - PI).abs();
+        if off_by > FLANK_THRESHOLD {
+            commands.entity(entity).try_insert(Flank { angle: slot });
+        } else {
+            commands.entity(entity).try_remove::<Flank>();
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use bevy::prelude::*;
+
+use crate::player::Player;
+use crate::world::{PlanetData, PLANET_SCALE};
+use crate::AppState;
+
+const CELL_SIZE: f32 = 40.0;
+/// Cells per side of the square grid centered on the player.
+const GRID_SIZE: usize = 64;
+/// The field is rebuilt once the player, or any planet, is this far from where it was built.
+const REBUILD_DISTANCE: f32 = 2.0 * CELL_SIZE;
+const PLANET_DRIFT: f32 = CELL_SIZE / 2.;
+/// Room kept around planets so enemy hulls don't scrape them.
+const CLEARANCE: f32 = 30.0;
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// Direction towards the player for every cell around it, going around planets.
+/// Built once for all enemies, who only look up the cell they're in.
+#[derive(Resource, Default)]
+pub struct FlowField {
+    /// Where the player was when the field was built.
+    center: Vec2,
+    /// World position of the grid's bottom left corner.
+    origin: Vec2,
+    /// Where the planets were when the field was built.
+    planets: Vec<Vec2>,
+    directions: Vec<Vec2>,
+}
+
+impl FlowField {
+    /// Way to go from `position`, or `None` outside the grid and on the player's own cell.
+    pub fn direction(&self, position: Vec2) -> Option<Vec2> {
+        let cell = self.cell(position)?;
+        let dir = *self.directions.get(cell)?;
+        (dir != Vec2::ZERO).then_some(dir)
+    }
+
+    fn cell(&self, position: Vec2) -> Option<usize> {
+        let local = ((position - self.origin) / CELL_SIZE).floor();
+        if local.x < 0. || local.y < 0. || local.x >= GRID_SIZE as f32 || local.y >= GRID_SIZE as f32 {
+            return None;
+        }
+        Some(local.y as usize * GRID_SIZE + local.x as usize)
+    }
+
+    fn cell_center(&self, cell: usize) -> Vec2 {
+        let (x, y) = (cell % GRID_SIZE, cell / GRID_SIZE);
+        self.origin + (Vec2::new(x as f32, y as f32) + 0.5) * CELL_SIZE
+    }
+
+    /// Neighbours of `cell` with the cost of stepping there, diagonals only
+    /// when they don't cut a blocked corner.
+    fn neighbours(cell: usize, blocked: &[bool]) -> impl Iterator<Item = (usize, u32)> + '_ {
+        let (x, y) = ((cell % GRID_SIZE) as i32, (cell / GRID_SIZE) as i32);
+        let open = move |x: i32, y: i32| {
+            (0..GRID_SIZE as i32).contains(&x) && (0..GRID_SIZE as i32).contains(&y) && !blocked[y as usize * GRID_SIZE + x as usize]
+        };
+        [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+            .into_iter()
+            .filter(move |(dx, dy)| open(x + dx, y + dy) && (*dx == 0 || *dy == 0 || (open(x + dx, y) && open(x, y + dy))))
+            .map(move |(dx, dy)| {
+                let cost = if dx == 0 || dy == 0 { STRAIGHT_COST } else { DIAGONAL_COST };
+                ((y + dy) as usize * GRID_SIZE + (x + dx) as usize, cost)
+            })
+    }
+
+    fn build(center: Vec2, planets: &PlanetData) -> Self {
+        let origin = center - Vec2::splat(GRID_SIZE as f32 * CELL_SIZE / 2.);
+        let planet_positions = planets.0.iter().map(|(position, _, _)| *position).collect();
+        let mut field = FlowField { center, origin, planets: planet_positions, directions: vec![Vec2::ZERO; GRID_SIZE * GRID_SIZE] };
+
+        // cells inside a planet point out of it, so anything pushed in there escapes
+        let mut blocked = vec![false; GRID_SIZE * GRID_SIZE];
+        for (cell, blocked) in blocked.iter_mut().enumerate() {
+            let position = field.cell_center(cell);
+            if let Some((planet_pos, _, _)) = planets
+                .0
+                .iter()
+                .find(|(planet_pos, radius, _)| position.distance(*planet_pos) < radius * PLANET_SCALE + CLEARANCE)
+            {
+                *blocked = true;
+                field.directions[cell] = (position - *planet_pos).normalize_or_zero();
+            }
+        }
+
+        // dijkstra outwards from the player's cell
+        let Some(goal) = field.cell(center) else {
+            return field;
+        };
+        blocked[goal] = false;
+        let mut costs = vec![u32::MAX; GRID_SIZE * GRID_SIZE];
+        let mut queue = BinaryHeap::new();
+        costs[goal] = 0;
+        queue.push(Reverse((0, goal)));
+        while let Some(Reverse((cost, cell))) = queue.pop() {
+            if cost > costs[cell] {
+                continue;
+            }
+            for (next, step) in FlowField::neighbours(cell, &blocked) {
+                if cost + step < costs[next] {
+                    costs[next] = cost + step;
+                    queue.push(Reverse((cost + step, next)));
+                }
+            }
+        }
+
+        for cell in 0..GRID_SIZE * GRID_SIZE {
+            if blocked[cell] || cell == goal || costs[cell] == u32::MAX {
+                continue;
+            }
+            if let Some((next, _)) = FlowField::neighbours(cell, &blocked).min_by_key(|(next, _)| costs[*next]) {
+                field.directions[cell] = (field.cell_center(next) - field.cell_center(cell)).normalize();
+            }
+        }
+        field
+    }
+
+    /// Whether a planet was added, removed or has drifted since the field was built.
+    fn planets_moved(&self, planets: &PlanetData) -> bool {
+        self.planets.len() != planets.0.len()
+            || self.planets.iter().zip(&planets.0).any(|(built, (position, _, _))| built.distance(*position) > PLANET_DRIFT)
+    }
+}
+
+pub struct FlowFieldPlugin;
+
+impl Plugin for FlowFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, update_flow_field.run_if(in_state(AppState::InGame)))
+            .insert_resource(FlowField::default());
+    }
+}
+
+fn update_flow_field(
+    mut field: ResMut<FlowField>,
+    player_query: Query<&Transform, With<Player>>,
+    planet_data: Res<PlanetData>,
+) {
+    let player = player_query.single().unwrap().translation.truncate();
+    let stale = field.directions.is_empty() || player.distance(field.center) > REBUILD_DISTANCE;
+    if stale || planet_data.is_changed() || field.planets_moved(&planet_data) {
+        *field = FlowField::build(player, &planet_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planet_at(position: Vec2) -> PlanetData {
+        PlanetData(vec![(position, 50., Entity::from_raw(1))])
+    }
+
+    #[test]
+    fn points_at_the_player_in_open_space() {
+        let field = FlowField::build(Vec2::ZERO, &PlanetData::default());
+        let dir = field.direction(Vec2::new(300., 0.)).unwrap();
+        assert!(dir.dot(Vec2::NEG_X) > 0.99);
+        assert_eq!(field.direction(Vec2::ZERO), None);
+        assert_eq!(field.direction(Vec2::splat(GRID_SIZE as f32 * CELL_SIZE)), None);
+    }
+
+    #[test]
+    fn goes_around_planets() {
+        let field = FlowField::build(Vec2::ZERO, &planet_at(Vec2::new(400., 0.)));
+        // straight behind the planet, heading at the player would run into it
+        let dir = field.direction(Vec2::new(700., 0.)).unwrap();
+        assert!(dir.dot(Vec2::NEG_X) < 0.99);
+        // inside the planet, the way out is away from its center
+        let dir = field.direction(Vec2::new(450., 20.)).unwrap();
+        assert!(dir.dot(Vec2::X) > 0.);
+    }
+
+    #[test]
+    fn notices_drifting_planets() {
+        let field = FlowField::build(Vec2::ZERO, &planet_at(Vec2::new(400., 0.)));
+        assert!(!field.planets_moved(&planet_at(Vec2::new(400. + PLANET_DRIFT / 2., 0.))));
+        assert!(field.planets_moved(&planet_at(Vec2::new(400. + PLANET_DRIFT * 2., 0.))));
+        assert!(field.planets_moved(&PlanetData::default()));
+    }
+}
@@ -1,10 +1,8 @@
-use bevy::color::Color::Srgba;
 use bevy::prelude::*;
-use bevy::prelude::Color::LinearRgba;
 use rand::Rng;
 
 
-use crate::{player::{Player, PreviousPosition}, AppState, GLOW_FACTOR};
+use crate::{player::{Player, PreviousPosition}, AppState, GLOW_FACTOR, SCALE};
 
 const TRAUMA_FALLOFF_SPEED: f32 = 6.0;
 
@@ -15,13 +13,41 @@ pub(crate) const PLAYER_COLOR: Color = Color::linear_rgb(5.0 / 255.0 * GLOW_FACT
 #[derive(Component, Default)]
 pub struct CircleCollider(pub f32);
 
+/// Player-facing toggles, flipped with keys while in game.
+#[derive(Resource)]
+pub struct Settings {
+    pub aim_assist: bool,
+    pub damage_numbers: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { aim_assist: true, damage_numbers: true }
+    }
+}
+
 pub struct GlobalPlugin;
 
 impl Plugin for GlobalPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Update, (fade_trail.after(spawn_trail),spawn_trail, apply_screen_shake).run_if(in_state(AppState::InGame)))
-            .insert_resource(ScreenShake::default());
+            .add_systems(Update, (fade_trail.after(spawn_trail),spawn_trail, apply_screen_shake, handle_settings_keys).run_if(in_state(AppState::InGame)))
+            .insert_resource(ScreenShake::default())
+            .insert_resource(Settings::default());
+    }
+}
+
+fn handle_settings_keys(
+    input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+) {
+    if input.just_pressed(KeyCode::KeyL) {
+        settings.aim_assist = !settings.aim_assist;
+        info!("Aim assist: {}", settings.aim_assist);
+    }
+    if input.just_pressed(KeyCode::KeyN) {
+        settings.damage_numbers = !settings.damage_numbers;
+        info!("Damage numbers: {}", settings.damage_numbers);
     }
 }
 
@@ -136,6 +162,17 @@ fn apply_screen_shake(
     }
 }
 
+/// Converts a point of the pixelated game world into the coordinates of the
+/// crisp cursor camera, so overlays can be drawn on top of it.
+pub fn world_to_overlay(
+    world_pos: Vec2,
+    (camera, camera_transform): (&Camera, &GlobalTransform),
+    (cursor_camera, cursor_transform): (&Camera, &GlobalTransform),
+) -> Option<Vec2> {
+    let viewport = camera.world_to_viewport(camera_transform, world_pos.extend(0.)).ok()?;
+    cursor_camera.viewport_to_world_2d(cursor_transform, viewport / SCALE).ok()
+}
+
 pub fn regular_polygon_vertices(radius: f32, sides: usize) -> Vec<Vec2> {
     (0..sides)
         .map(|i| {
@@ -151,7 +188,7 @@ pub fn adjusted_glow(color: Color, base_glow: f32) -> Color {
     let g = srgb.green;
     let b = srgb.blue;
     let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
-    let correction = (1.0 / luminance.clamp(0.2, 1.0));
+    let correction = 1.0 / luminance.clamp(0.2, 1.0);
     let glow_factor = base_glow * correction;
     Color::linear_rgba(r * glow_factor, g * glow_factor, b * glow_factor, srgb.alpha)
 }
@@ -9,13 +9,30 @@ use bevy::{audio::Volume, prelude::*};
 use bevy_hanabi::prelude::*;
 
 use crate::AppState::{InGame, MainMenu};
+use crate::arrow::ArrowPlugin;
+use crate::behaviour::BehaviourPlugin;
+use crate::boss::BossPlugin;
+use crate::crosshair::CrosshairPlugin;
+use crate::damage::DamagePlugin;
+use crate::damage_numbers::DamageNumbersPlugin;
+use crate::element::ElementPlugin;
+use crate::elite::ElitePlugin;
+use crate::feedback::FeedbackPlugin;
+use crate::flocking::FlockingPlugin;
+use crate::morale::MoralePlugin;
+use crate::nest::NestPlugin;
+use crate::flow_field::FlowFieldPlugin;
 use crate::enemy::EnemyPlugin;
 use crate::global::ENEMY_COLOR;
 use crate::particles::ParticlePlugin;
 use crate::planets::PlanetPlugin;
+use crate::projectile::ProjectilePlugin;
+use crate::raider::RaiderPlugin;
 use crate::player::spawn_player;
 use crate::sfx::SFXPlugin;
+use crate::spawning::SpawningPlugin;
 use crate::ui::UIPlugin;
+use crate::upgrades::UpgradePlugin;
 use crate::world::WorldPlugin;
 use crate::xp::XPPlugin;
 use crate::{global::GlobalPlugin, player::PlayerPlugin};
@@ -23,13 +40,30 @@ use bevy_rapier2d::plugin::{NoUserData, RapierPhysicsPlugin};
 use bevy_rapier2d::prelude::RapierConfiguration;
 
 pub mod arrow;
+pub mod behaviour;
+pub mod boss;
+pub mod crosshair;
+pub mod damage;
+pub mod damage_numbers;
+pub mod element;
+pub mod elite;
 pub mod enemy;
+pub mod feedback;
+pub mod flocking;
+pub mod flow_field;
 pub mod global;
+pub mod morale;
+pub mod nest;
 pub mod particles;
+pub mod physics;
 pub mod planets;
 pub mod player;
+pub mod projectile;
+pub mod raider;
 pub mod sfx;
+pub mod spawning;
 pub mod ui;
+pub mod upgrades;
 pub mod world;
 pub mod xp;
 
@@ -75,6 +109,25 @@ fn main() {
             UIPlugin,
             PlanetPlugin,
             SFXPlugin,
+            ArrowPlugin,
+            UpgradePlugin,
+            CrosshairPlugin,
+        ))
+        .add_plugins((
+            DamagePlugin,
+            DamageNumbersPlugin,
+            FeedbackPlugin,
+            ProjectilePlugin,
+            ElitePlugin,
+            BossPlugin,
+            BehaviourPlugin,
+            FlowFieldPlugin,
+            FlockingPlugin,
+            MoralePlugin,
+            SpawningPlugin,
+            NestPlugin,
+            RaiderPlugin,
+            ElementPlugin,
         ))
         .run();
 }
@@ -115,7 +168,7 @@ fn setup(
         ))
         .with_children(|parent| {
             parent.spawn((
-                Camera2d::default(),
+                Camera2d,
                 Camera {
                     hdr: true,
                     target: image_handle.clone().into(),
@@ -143,7 +196,7 @@ fn setup(
 
     ));
 
-    commands.spawn((Camera2d::default(), RenderLayers::layer(1), CursorCamera));
+    commands.spawn((Camera2d, RenderLayers::layer(1), CursorCamera));
 
     commands.spawn((
         AudioPlayer::<AudioSource>(assets.load("music1.wav")),
@@ -157,7 +210,7 @@ fn setup(
 }
 fn setup_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
     info!("Spawning main menu");
-    commands.spawn(Camera2d::default());
+    commands.spawn(Camera2d);
     commands
         .spawn(Node {
             width: Val::Percent(100.),
@@ -227,7 +280,7 @@ fn spawn_button(
                 ..default()
             },
             BackgroundColor(DARK_GRAY.into()),
-            Button::default(),
+            Button,
             MenuButtonAction(text.to_string()),
         ))
         .with_children(|button| {
@@ -249,10 +302,10 @@ fn despawn_menu(
     camera: Query<(Entity, &Camera2d)>,
 ) {
     for entity in &query {
-        commands.entity(entity).despawn_recursive();
+        commands.entity(entity).despawn();
     }
     for cam in camera {
-        commands.entity(cam.0).despawn_recursive();
+        commands.entity(cam.0).despawn();
     }
 }
 
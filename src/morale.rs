@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+
+use crate::behaviour::Action;
+use crate::damage::{DamageDealt, DamageSystems, DamageTarget};
+use crate::enemy::{Enemy, HP};
+use crate::feedback::DamageFlash;
+use crate::player::Player;
+use crate::projectile::Shooter;
+use crate::AppState;
+
+/// Enemies break and run below this much morale.
+const BREAK_POINT: f32 = 0.3;
+/// ...and come back once they've recovered this much.
+const RALLY_POINT: f32 = 0.8;
+/// Morale lost per share of max HP taken as damage.
+const DAMAGE_MORALE: f32 = 1.2;
+const ALLY_DEATH_RADIUS: f32 = 250.0;
+const ALLY_DEATH_MORALE: f32 = 0.25;
+/// Morale regained per second, and extra per ally close by while regrouping.
+const RECOVERY: f32 = 0.05;
+const RECOVERY_PER_ALLY: f32 = 0.04;
+/// Retreating enemies stop running this far from the player and regroup.
+const RETREAT_DISTANCE: f32 = 650.0;
+/// Regrouping enemies run again if the player comes this close.
+const PURSUIT_DISTANCE: f32 = 400.0;
+const RALLY_RADIUS: f32 = 400.0;
+/// Ranged enemies back off to here instead of running, still in firing range.
+const KITE_DISTANCE: f32 = 380.0;
+/// Mix of a retreating enemy's color towards a washed out grey.
+const RETREAT_TINT: f32 = 0.85;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MoraleState {
+    Steady,
+    /// Running out of the player's range, or kiting for ranged enemies.
+    Retreating,
+    /// Out of range, gathering with allies until morale is back.
+    Regrouping,
+}
+
+#[derive(Component)]
+pub struct Morale {
+    pub value: f32,
+    state: MoraleState,
+    rally: Vec2,
+    /// Own color, captured before the first retreat tint.
+    base: Option<Color>,
+    tinted: bool,
+}
+
+impl Default for Morale {
+    fn default() -> Self {
+        Morale { value: 1., state: MoraleState::Steady, rally: Vec2::ZERO, base: None, tinted: false }
+    }
+}
+
+impl Morale {
+    /// Movement replacing the brain's own while the enemy is shaken.
+    pub fn action(&self, ranged: bool) -> Option<Action> {
+        match self.state {
+            MoraleState::Steady => None,
+            MoraleState::Retreating if ranged => Some(Action::Kite { distance: KITE_DISTANCE }),
+            MoraleState::Retreating => Some(Action::Flee),
+            MoraleState::Regrouping => Some(Action::Regroup { rally: self.rally }),
+        }
+    }
+
+    pub fn shaken(&self) -> bool {
+        self.state != MoraleState::Steady
+    }
+}
+
+pub struct MoralePlugin;
+
+impl Plugin for MoralePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                update_morale,
+                tint_shaken_enemies,
+                lose_morale.in_set(DamageSystems::React),
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+fn lose_morale(
+    mut dealt: EventReader<DamageDealt>,
+    mut morale_query: Query<(Entity, &Transform, &mut Morale, &HP)>,
+) {
+    for event in dealt.read() {
+        if event.target_kind != DamageTarget::Enemy {
+            continue;
+        }
+        if !event.killed {
+            if let Ok((_, _, mut morale, hp)) = morale_query.get_mut(event.target) {
+                morale.value -= event.amount / hp.max * DAMAGE_MORALE;
+            }
+            continue;
+        }
+        for (entity, transform, mut morale, _) in &mut morale_query {
+            if entity != event.target && transform.translation.truncate().distance(event.position) < ALLY_DEATH_RADIUS {
+                morale.value -= ALLY_DEATH_MORALE;
+            }
+        }
+    }
+}
+
+fn update_morale(
+    mut morale_query: Query<(&Transform, &mut Morale, Has<Shooter>)>,
+    ally_query: Query<&Transform, With<Enemy>>,
+    player_query: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+) {
+    let player = player_query.single().unwrap().translation.truncate();
+    for (transform, mut morale, ranged) in &mut morale_query {
+        let position = transform.translation.truncate();
+        let distance = position.distance(player);
+        let mut recovery = RECOVERY;
+        if morale.state == MoraleState::Regrouping {
+            // head for the middle of whoever is around, taking heart from them
+            let allies: Vec<Vec2> = ally_query
+                .iter()
+                .map(|ally| ally.translation.truncate())
+                .filter(|ally| ally.distance(position) < RALLY_RADIUS)
+                .collect();
+            morale.rally = allies.iter().sum::<Vec2>() / allies.len().max(1) as f32;
+            recovery += RECOVERY_PER_ALLY * allies.len().saturating_sub(1) as f32;
+        }
+        morale.value = (morale.value + recovery * time.delta_secs()).min(1.);
+
+        let next = match morale.state {
+            MoraleState::Steady if morale.value < BREAK_POINT => MoraleState::Retreating,
+            // kiting enemies stay in range and just wait to recover
+            MoraleState::Retreating if ranged && morale.value >= RALLY_POINT => MoraleState::Steady,
+            MoraleState::Retreating if !ranged && distance > RETREAT_DISTANCE => MoraleState::Regrouping,
+            MoraleState::Regrouping if morale.value >= RALLY_POINT => MoraleState::Steady,
+            MoraleState::Regrouping if distance < PURSUIT_DISTANCE => MoraleState::Retreating,
+            state => state,
+        };
+        morale.state = next;
+    }
+}
+
+/// Washes out the color of enemies that lost their nerve.
+fn tint_shaken_enemies(
+    morale_query: Query<(&mut Morale, &MeshMaterial2d<ColorMaterial>, Has<DamageFlash>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (mut morale, material, flashing) in morale_query {
+        // the flash restores the color it started from, wait for it to finish
+        if flashing || morale.tinted == morale.shaken() {
+            continue;
+        }
+        let Some(material) = materials.get_mut(&material.0) else {
+            continue;
+        };
+        let base = *morale.base.get_or_insert(material.color);
+        morale.tinted = morale.shaken();
+        material.color = if morale.tinted {
+            base.mix(&Color::linear_rgba(0.4, 0.4, 0.6, base.alpha()), RETREAT_TINT)
+        } else {
+            base
+        };
+    }
+}
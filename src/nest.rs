@@ -0,0 +1,135 @@
+use std::f32::consts::TAU;
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use rand::prelude::*;
+
+use crate::elite::roll_modifiers;
+use crate::enemy::{Enemy, HP};
+use crate::particles::ParticleHandles;
+use crate::player::Player;
+use crate::spawning::{polygon_outline, spawn_telegraph};
+use crate::world::{roll_sides, Difficulty, PLANET_SCALE};
+use crate::{AppState, FirstPass, ENEMY_COLOR};
+
+/// Chance of a planet hosting a nest.
+pub const NEST_CHANCE: f64 = 0.2;
+/// Extra crystal levels of a nested planet, bringing it up to but not past
+/// the boss's guaranteed tier.
+pub const NEST_CRYSTAL_BONUS: i32 = 1;
+const NEST_INTERVAL: f32 = 12.0;
+const MAX_GUARDS: usize = 4;
+/// Gap between the planet's edge and where its guards patrol.
+const PATROL_GAP: f32 = 60.0;
+/// Guards go after the player once it comes this close to their nest.
+const AGGRO_DISTANCE: f32 = 450.0;
+const MARKER_WIDTH: f32 = 4.0;
+
+/// Planet that breeds enemies to guard it.
+#[derive(Component)]
+pub struct Nest {
+    cooldown: f32,
+    /// Distance from the planet's center its guards patrol at.
+    patrol_radius: f32,
+}
+
+/// Enemy patrolling around the nest it hatched from, until the player shows up.
+#[derive(Component)]
+pub struct Guard {
+    pub nest: Entity,
+    pub home: Vec2,
+    pub radius: f32,
+}
+
+impl Nest {
+    pub fn patrol_radius(&self) -> f32 {
+        self.patrol_radius
+    }
+}
+
+pub struct NestPlugin;
+
+impl Plugin for NestPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                breed_guards,
+                release_guards,
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Turns `planet` into a nest, ringed by an outline in the enemy color.
+pub fn make_nest(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    planet: Entity,
+    radius: f32,
+) {
+    let mut rng = rand::rng();
+    commands
+        .entity(planet)
+        .insert(Nest {
+            cooldown: rng.random_range(0.0..NEST_INTERVAL),
+            patrol_radius: radius * PLANET_SCALE + PATROL_GAP,
+        })
+        .with_children(|parent| {
+            // planets are scaled up, the marker with them
+            parent.spawn((
+                Mesh2d(meshes.add(polygon_outline(radius + MARKER_WIDTH * 2., 12, MARKER_WIDTH))),
+                MeshMaterial2d(materials.add(ColorMaterial::from_color(ENEMY_COLOR))),
+                Transform::from_xyz(0., 0., -0.1),
+                RenderLayers::layer(0),
+                FirstPass,
+            ));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn breed_guards(
+    nest_query: Query<(Entity, &mut Nest, &Transform)>,
+    guard_query: Query<&Guard>,
+    difficulty: Res<Difficulty>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    particle_handles: Res<ParticleHandles>,
+    time: Res<Time>,
+) {
+    let mut rng = rand::rng();
+    for (entity, mut nest, transform) in nest_query {
+        nest.cooldown -= time.delta_secs();
+        if nest.cooldown > 0. {
+            continue;
+        }
+        nest.cooldown = NEST_INTERVAL;
+        if guard_query.iter().filter(|guard| guard.nest == entity).count() >= MAX_GUARDS {
+            continue;
+        }
+        let position = transform.translation.truncate() + Vec2::from_angle(rng.random_range(0.0..TAU)) * nest.patrol_radius;
+        let enemy = Enemy::new(roll_sides(&mut rng, difficulty.0));
+        let modifiers = roll_modifiers(&mut rng, &enemy, difficulty.0);
+        spawn_telegraph(&mut commands, &mut meshes, &mut materials, &particle_handles, enemy, modifiers, position, Some(entity));
+    }
+}
+
+/// Sends the guards of a nest after the player once it gets close or hurts one of
+/// them. Guards of a destroyed nest are on their own.
+fn release_guards(
+    guard_query: Query<(Entity, &Guard, &HP)>,
+    nest_query: Query<(), With<Nest>>,
+    player_query: Query<&Transform, With<Player>>,
+    mut commands: Commands,
+) {
+    let player = player_query.single().unwrap().translation.truncate();
+    let alerted: Vec<Entity> = guard_query
+        .iter()
+        .filter(|(_, guard, hp)| guard.home.distance(player) < AGGRO_DISTANCE || hp.current < hp.max)
+        .map(|(_, guard, _)| guard.nest)
+        .collect();
+    for (entity, guard, _) in &guard_query {
+        if alerted.contains(&guard.nest) || nest_query.get(guard.nest).is_err() {
+            commands.entity(entity).try_remove::<Guard>();
+        }
+    }
+}
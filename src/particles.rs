@@ -7,7 +7,7 @@ impl Plugin for ParticlePlugin {
     fn build(&self, app: &mut App) {
         app
             .insert_resource(ParticleHandles::default())
-            .add_systems(Startup, (setup_xp_trail_particles,setup_enemy_death_particles, setup_enemy_damage_particles, setup_arrow_trail_particles));
+            .add_systems(Startup, (setup_xp_trail_particles,setup_enemy_death_particles, setup_enemy_damage_particles, setup_arrow_trail_particles, setup_crit_flash_particles, setup_bash_swing_particles, setup_enemy_projectile_particles, setup_spawn_telegraph_particles, setup_portal_particles));
     }
 }
 
@@ -17,7 +17,12 @@ pub struct ParticleHandles {
     pub enemy_death: Handle<EffectAsset>,
     pub enemy_damage: Handle<EffectAsset>,
     pub arrow_trail: Handle<EffectAsset>,
-    pub xp_trail: Handle<EffectAsset>
+    pub xp_trail: Handle<EffectAsset>,
+    pub crit_flash: Handle<EffectAsset>,
+    pub bash_swing: Handle<EffectAsset>,
+    pub enemy_projectile: Handle<EffectAsset>,
+    pub spawn_telegraph: Handle<EffectAsset>,
+    pub portal: Handle<EffectAsset>,
 }
 
 fn setup_enemy_death_particles(
@@ -172,4 +177,195 @@ fn setup_xp_trail_particles(
     .render(ColorOverLifetimeModifier {gradient, ..default()});
 
     particle_handles.xp_trail = effects.add(effect);
-}
\ No newline at end of file
+}
+
+fn setup_crit_flash_particles(
+    mut particle_handles: ResMut<ParticleHandles>,
+    mut effects: ResMut<Assets<EffectAsset>>
+) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0., Vec4::new(20., 16., 2., 1.));
+    gradient.add_key(1., Vec4::splat(0.));
+
+    let mut module = Module::default();
+
+    let init_pos = SetPositionSphereModifier {
+        center: module.lit(Vec3::ZERO),
+        radius: module.lit(5.),
+        dimension: ShapeDimension::Surface,
+    };
+
+    let init_vel = SetVelocitySphereModifier {
+        speed: module.lit(150.),
+        center: module.lit(Vec3::ZERO),
+    };
+
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(0.3));
+    let init_size = SetAttributeModifier::new(Attribute::SIZE, module.lit(4.0));
+
+    let effect = EffectAsset::new(
+        3000,
+        SpawnerSettings::once(30.0.into()),
+        module
+    )
+    .init(init_pos)
+    .init(init_vel)
+    .init(init_lifetime)
+    .init(init_size)
+    .render(ColorOverLifetimeModifier {gradient, ..default()});
+
+    particle_handles.crit_flash = effects.add(effect);
+}
+
+fn setup_bash_swing_particles(
+    mut particle_handles: ResMut<ParticleHandles>,
+    mut effects: ResMut<Assets<EffectAsset>>
+) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0., Vec4::new(4., 8., 12., 1.));
+    gradient.add_key(1., Vec4::splat(0.));
+
+    let mut module = Module::default();
+
+    let init_pos = SetPositionSphereModifier {
+        center: module.lit(Vec3::ZERO),
+        radius: module.lit(25.),
+        dimension: ShapeDimension::Volume,
+    };
+
+    let init_vel = SetVelocitySphereModifier {
+        speed: module.lit(220.),
+        center: module.lit(Vec3::ZERO),
+    };
+
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(0.2));
+    let init_size = SetAttributeModifier::new(Attribute::SIZE, module.lit(4.0));
+
+    let effect = EffectAsset::new(
+        3000,
+        SpawnerSettings::once(40.0.into()),
+        module
+    )
+    .init(init_pos)
+    .init(init_vel)
+    .init(init_lifetime)
+    .init(init_size)
+    .render(ColorOverLifetimeModifier {gradient, ..default()});
+
+    particle_handles.bash_swing = effects.add(effect);
+}
+
+fn setup_enemy_projectile_particles(
+    mut particle_handles: ResMut<ParticleHandles>,
+    mut effects: ResMut<Assets<EffectAsset>>
+) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0., Vec4::new(4., 0.8, 1.2, 1.));
+    gradient.add_key(1., Vec4::splat(0.));
+
+    let mut module = Module::default();
+
+    let init_pos = SetPositionSphereModifier {
+        center: module.lit(Vec3::ZERO),
+        radius: module.lit(2.),
+        dimension: ShapeDimension::Volume,
+    };
+
+    let init_vel = SetVelocitySphereModifier {
+        speed: module.lit(25.),
+        center: module.lit(Vec3::ZERO),
+    };
+
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(0.35));
+    let init_size = SetAttributeModifier::new(Attribute::SIZE, module.lit(3.5));
+
+    let effect = EffectAsset::new(
+        3000,
+        SpawnerSettings::rate(30.0.into()),
+        module
+    )
+    .init(init_pos)
+    .init(init_vel)
+    .init(init_lifetime)
+    .init(init_size)
+    .render(ColorOverLifetimeModifier {gradient, ..default()});
+
+    particle_handles.enemy_projectile = effects.add(effect);
+}
+
+fn setup_spawn_telegraph_particles(
+    mut particle_handles: ResMut<ParticleHandles>,
+    mut effects: ResMut<Assets<EffectAsset>>
+) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0., Vec4::new(6., 1., 1.5, 1.));
+    gradient.add_key(1., Vec4::splat(0.));
+
+    let mut module = Module::default();
+
+    let init_pos = SetPositionSphereModifier {
+        center: module.lit(Vec3::ZERO),
+        radius: module.lit(40.),
+        dimension: ShapeDimension::Surface,
+    };
+
+    // sucked into the middle, where the enemy is about to appear
+    let init_vel = SetVelocitySphereModifier {
+        speed: module.lit(-80.),
+        center: module.lit(Vec3::ZERO),
+    };
+
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(0.5));
+    let init_size = SetAttributeModifier::new(Attribute::SIZE, module.lit(3.0));
+
+    let effect = EffectAsset::new(
+        3000,
+        SpawnerSettings::rate(60.0.into()),
+        module
+    )
+    .init(init_pos)
+    .init(init_vel)
+    .init(init_lifetime)
+    .init(init_size)
+    .render(ColorOverLifetimeModifier {gradient, ..default()});
+
+    particle_handles.spawn_telegraph = effects.add(effect);
+}
+
+fn setup_portal_particles(
+    mut particle_handles: ResMut<ParticleHandles>,
+    mut effects: ResMut<Assets<EffectAsset>>
+) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0., Vec4::new(6., 1.5, 10., 1.));
+    gradient.add_key(1., Vec4::splat(0.));
+
+    let mut module = Module::default();
+
+    let init_pos = SetPositionSphereModifier {
+        center: module.lit(Vec3::ZERO),
+        radius: module.lit(60.),
+        dimension: ShapeDimension::Surface,
+    };
+
+    let init_vel = SetVelocitySphereModifier {
+        speed: module.lit(-60.),
+        center: module.lit(Vec3::ZERO),
+    };
+
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(0.8));
+    let init_size = SetAttributeModifier::new(Attribute::SIZE, module.lit(4.0));
+
+    let effect = EffectAsset::new(
+        3000,
+        SpawnerSettings::rate(40.0.into()),
+        module
+    )
+    .init(init_pos)
+    .init(init_vel)
+    .init(init_lifetime)
+    .init(init_size)
+    .render(ColorOverLifetimeModifier {gradient, ..default()});
+
+    particle_handles.portal = effects.add(effect);
+}
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+/// Category of every rapier collider in the game. Decides what it touches
+/// ([`CollisionGroups`]) and what it bounces off ([`SolverGroups`]).
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionLayer {
+    Player,
+    Enemy,
+    Arrow,
+    Planet,
+    Orb,
+    EnemyProjectile,
+    Portal,
+}
+
+impl CollisionLayer {
+    pub fn group(self) -> Group {
+        match self {
+            CollisionLayer::Player => Group::GROUP_1,
+            CollisionLayer::Enemy => Group::GROUP_2,
+            CollisionLayer::Arrow => Group::GROUP_3,
+            CollisionLayer::Planet => Group::GROUP_4,
+            CollisionLayer::Orb => Group::GROUP_5,
+            CollisionLayer::EnemyProjectile => Group::GROUP_6,
+            CollisionLayer::Portal => Group::GROUP_7,
+        }
+    }
+
+    /// Layers this one generates contacts and collision events with.
+    /// Rapier only pairs two colliders when both of them list each other.
+    pub fn interacts_with(self) -> Group {
+        use CollisionLayer::*;
+        let layers: &[CollisionLayer] = match self {
+            // arrows leave the bow inside the player, so they ignore it
+            Player => &[Enemy, Planet, Orb, EnemyProjectile, Portal],
+            Enemy => &[Player, Enemy, Arrow, Planet],
+            // arrows never hit each other, a multishot fan would collapse
+            Arrow => &[Enemy, Planet, Portal],
+            Planet => &[Player, Enemy, Arrow, Planet, EnemyProjectile],
+            // orbs are sensors that only the player picks up
+            Orb => &[Player],
+            // enemy shots fly through other enemies and stop at planets
+            EnemyProjectile => &[Player, Planet],
+            // enemies pour out of portals, only the player and arrows bump into them
+            Portal => &[Player, Arrow],
+        };
+        layers.iter().fold(Group::NONE, |group, layer| group | layer.group())
+    }
+
+    /// Layers this one physically bounces off.
+    pub fn solves_with(self) -> Group {
+        let sensors = CollisionLayer::Orb.group() | CollisionLayer::EnemyProjectile.group();
+        if self.group().intersects(sensors) {
+            return Group::NONE;
+        }
+        self.interacts_with() & !sensors
+    }
+
+    pub fn collision_groups(self) -> CollisionGroups {
+        CollisionGroups::new(self.group(), self.interacts_with())
+    }
+
+    pub fn solver_groups(self) -> SolverGroups {
+        SolverGroups::new(self.group(), self.solves_with())
+    }
+
+    pub fn bundle(self) -> impl Bundle {
+        (self, self.collision_groups(), self.solver_groups())
+    }
+}
+
+/// Entities of a started collision where one side is on `layer`: returns that
+/// entity first, then the other one and its layer.
+pub fn started_collision(
+    event: &CollisionEvent,
+    layers: &Query<&CollisionLayer>,
+    layer: CollisionLayer,
+) -> Option<(Entity, Entity, CollisionLayer)> {
+    let CollisionEvent::Started(e1, e2, _) = event else {
+        return None;
+    };
+    let (l1, l2) = (layers.get(*e1).ok()?, layers.get(*e2).ok()?);
+    if *l1 == layer {
+        Some((*e1, *e2, *l2))
+    } else if *l2 == layer {
+        Some((*e2, *e1, *l1))
+    } else {
+        None
+    }
+}
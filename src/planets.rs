@@ -1,14 +1,10 @@
-use std::ops::DerefMut;
 use bevy::prelude::*;
-use bevy_hanabi::ParticleEffect;
-use bevy_rapier2d::prelude::*;
 use rand::Rng;
 use crate::AppState;
-use crate::arrow::Arrow;
-use crate::global::ScreenShake;
-use crate::particles::ParticleHandles;
+use crate::damage::{DamageDealt, DamageSystems};
 use crate::player::{ColorId, Crystal, Inventory};
-use crate::sfx::SFX;
+use crate::raider::Raider;
+use crate::world::PlanetData;
 
 #[derive(Component, Clone)]
 pub struct Planet {
@@ -23,12 +19,18 @@ pub struct Effect {
     pub level: i32
 }
 
-#[derive(Default, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum EffectType {
     #[default]
     Poison,
     Speed,
-    Fire
+    Fire,
+    Split,
+    Focus,
+}
+
+impl EffectType {
+    pub const ALL: [EffectType; 5] = [EffectType::Poison, EffectType::Speed, EffectType::Fire, EffectType::Split, EffectType::Focus];
 }
 
 pub struct PlanetPlugin;
@@ -36,65 +38,50 @@ pub struct PlanetPlugin;
 impl Plugin for PlanetPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Update, (collision_events_system).run_if(in_state(AppState::InGame)));
+            .add_systems(PreUpdate, sync_planet_data.run_if(in_state(AppState::InGame)))
+            .add_systems(Update, destroy_planets.in_set(DamageSystems::Despawn).run_if(in_state(AppState::InGame)));
     }
 }
 
-fn collision_events_system(
-    mut collision_events: EventReader<CollisionEvent>,
-    mut planet_query: Query<(&mut Planet, Entity)>,
-    mut arrow_query: Query<(&mut Arrow, &Transform, Entity)>,
-    mut commands: Commands,
-    mut shake: ResMut<ScreenShake>,
-    mut inventory: ResMut<Inventory>,
-    particle_handles: Res<ParticleHandles>,
-    sfx: Res<SFX>,
+/// Planets drift when pushed around, so keeps `PlanetData` at their live positions.
+/// Only adding or removing planets marks it as changed, drifting doesn't.
+#[allow(clippy::type_complexity)]
+fn sync_planet_data(
+    planet_query: Query<(Entity, &Transform), (With<Planet>, Changed<Transform>)>,
+    mut planet_data: ResMut<PlanetData>,
 ) {
-    for event in collision_events.read() {
-        match event {
-            CollisionEvent::Started(e1, e2, _flags) => {
-
-                if let Ok(mut planet) = planet_query.get_mut(*e1) {
-                    if let Ok(mut arrow) = arrow_query.get_mut(*e2) {
-                        collision(&sfx, planet.0.deref_mut(), arrow.0.deref_mut(),  &mut arrow.2.clone(),&arrow.1.clone().translation.truncate(), &mut planet.1.clone(), &mut commands, &mut shake, &particle_handles, &mut inventory);
-                    }
-                } else if let Ok(mut planet) = planet_query.get_mut(*e2) {
-                    if let Ok(mut arrow) = arrow_query.get_mut(*e1) {
-                        collision(&sfx, planet.0.deref_mut(), arrow.0.deref_mut(),  &mut arrow.2.clone(),&arrow.1.clone().translation.truncate(), &mut planet.1.clone(), &mut commands, &mut shake, &particle_handles, &mut inventory);
-                    }
-                }
-
-            },
-            _ => ()
+    let planet_data = planet_data.bypass_change_detection();
+    for (entity, transform) in planet_query {
+        if let Some((position, _, _)) = planet_data.0.iter_mut().find(|(_, _, planet)| *planet == entity) {
+            *position = transform.translation.truncate();
         }
     }
 }
 
-fn collision(
-    sfx: &Res<SFX>,
-    planet: &mut Planet,
-    arrow: &mut Arrow,
-    arrow_ent: &mut Entity,
-    arrow_pos: &Vec2,
-    planet_ent: &mut Entity,
-    commands: &mut Commands,
-    shake: &mut ResMut<ScreenShake>,
-    particle_handles: &Res<ParticleHandles>,
-    inventory: &mut ResMut<Inventory>,
+fn destroy_planets(
+    mut dealt: EventReader<DamageDealt>,
+    planet_query: Query<&Planet>,
+    mut commands: Commands,
+    mut inventory: ResMut<Inventory>,
+    mut planet_data: ResMut<PlanetData>,
+    mut raider_query: Query<&mut Raider>,
 ) {
-    planet.hp -= arrow.damage;
-    commands.entity(*arrow_ent).despawn();
-    shake.trauma = 1.0;
-    commands.spawn((
-        ParticleEffect::new(particle_handles.enemy_damage.clone()),
-        Transform::from_translation(arrow_pos.extend(0.0))
-    ));
-    commands.spawn(AudioPlayer(sfx.hurt.clone()));
-    
     let mut rng = rand::rng();
-
-    if planet.hp <= 0.0 {
-        commands.entity(*planet_ent).despawn();
-        inventory.crystals.push(Crystal { color: ColorId::from_bevy(&planet.color.clone(), true).unwrap(), effect: planet.effect.clone(), phase: rng.random(), resonance: rng.random()});
+    for event in dealt.read() {
+        if !event.killed {
+            continue;
+        }
+        if let Ok(planet) = planet_query.get(event.target) {
+            commands.entity(event.target).despawn();
+            planet_data.0.retain(|(_, _, entity)| *entity != event.target);
+            let crystal = Crystal { color: ColorId::from_bevy(&planet.color, true).unwrap(), effect: planet.effect.clone(), phase: rng.random(), resonance: rng.random()};
+            // whoever got the last hit in gets the crystal
+            if let Some(mut raider) = event.attacker.and_then(|attacker| raider_query.get_mut(attacker).ok()) {
+                info!("A raider stole a {:?} crystal.", crystal.effect.effect_type);
+                raider.steal(crystal);
+            } else {
+                inventory.crystals.push(crystal);
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,13 +1,17 @@
-use crate::arrow::MAX_ARROW_SPEED;
+use crate::arrow::{aim_spread, aim_wobble, arrow_damage, arrow_travel_distance, arrow_velocity, fan_angles, launch_power, ChargeState, Quiver, ARROW_DAMPING, FULL_CHARGE, MAX_CHARGE};
+use crate::damage::{DamageEvent, DamageSource, DamageType};
+use crate::enemy::Enemy;
+use crate::physics::CollisionLayer;
+use crate::sfx::SFX;
+use crate::upgrades::Upgrades;
 use crate::global::{ScreenShake, regular_polygon_vertices};
-use crate::planets::Effect;
+use crate::planets::{Effect, EffectType, Planet};
 use crate::{
     AppState, FirstPass, GLOW_FACTOR, SCALE,
-    arrow::{ARROW_SPEED, Arrow},
+    arrow::Arrow,
     global::{CircleCollider, PLAYER_COLOR},
     particles::ParticleHandles,
-    ui::{HealthBarSegment, PlayerHealthBar, XPBar},
-    xp,
+    ui::{AmmoCounter, EquippedCrystalText, HealthBarSegment, PlayerHealthBar, XPBar},
 };
 use bevy::render::view::RenderLayers;
 use bevy::{
@@ -16,13 +20,20 @@ use bevy::{
 };
 use bevy_hanabi::ParticleEffect;
 use bevy_rapier2d::prelude::*;
-use std::collections::HashSet;
 use rand::Rng;
 
 const PLAYER_SPEED: f32 = 200.0;
 const KNOCKBACK: f32 = 20.0;
 const BOW_OFFSET: f32 = 55.0;
 const XP_PER_LEVEL: f32 = 10.;
+const PREVIEW_BOUNCES: usize = 2;
+const PREVIEW_DOT_SPACING: f32 = 14.0;
+const ARROW_HALF_EXTENTS: Vec2 = Vec2::new(27.0, 3.15);
+const BASH_RANGE: f32 = 110.0;
+const BASH_ARC: f32 = 1.2;
+const BASH_DAMAGE: f32 = 1.0;
+const BASH_KNOCKBACK: f32 = 450.0;
+const BASH_COOLDOWN: f32 = 1.2;
 
 #[derive(Component)]
 #[require(Velocity, Mesh2d, MeshMaterial2d<ColorMaterial>)]
@@ -56,9 +67,33 @@ pub struct PlayerHealth {
 #[derive(Resource, Default)]
 pub struct Inventory {
     pub crystals: Vec<Crystal>,
+    pub equipped: Option<Crystal>,
 }
 
 impl Inventory {
+    /// Moves the crystal at `index` into the bow. Returns true if a previously
+    /// equipped crystal took its place in the list.
+    pub fn equip(&mut self, index: usize) -> bool {
+        if index >= self.crystals.len() {
+            warn!("Index out of bounds: {}", index);
+            return false;
+        }
+        let crystal = self.crystals.remove(index);
+        if let Some(old) = self.equipped.replace(crystal) {
+            self.crystals.insert(index, old);
+            return true;
+        }
+        false
+    }
+
+    /// Level of the equipped crystal if it carries the given effect, 0 otherwise.
+    pub fn equipped_level(&self, effect_type: EffectType) -> i32 {
+        self.equipped
+            .as_ref()
+            .filter(|crystal| crystal.effect.effect_type == effect_type)
+            .map_or(0, |crystal| crystal.effect.level.max(0))
+    }
+
     pub fn sell(&mut self, index: usize, mut xp_bar_query: Query<&mut XPBar>) {
         if index >= self.crystals.len() {
             warn!("Index out of bounds: {}", index);
@@ -91,20 +126,20 @@ impl Inventory {
 
         let level = (avrg_level * (1. - alignment) * 2.) as i32;
 
-        let effect_type = if rng.random::<bool>() { &crystal1.effect.effect_type } else { &crystal2.effect.effect_type };
+        let effect_type = if rng.random::<bool>() { crystal1.effect.effect_type } else { crystal2.effect.effect_type };
 
         let phase = self.bounded_random_around((crystal1.phase - crystal2.phase) / 2., alignment, 0.5, &mut rng);
         let resonance = self.bounded_random_around((crystal1.resonance - crystal2.resonance) / 2., alignment, 0.5, &mut rng);
 
-        let r = ((( crystal1.color.r as i32 + crystal2.color.r as i32) % 255 / 2)) as u8;
-        let g = ((( crystal1.color.g as i32 + crystal2.color.g as i32) % 255 / 2)) as u8;
-        let blue = ((( crystal1.color.b as i32 + crystal2.color.b as i32) % 255 / 2)) as u8;
+        let r = (( crystal1.color.r as i32 + crystal2.color.r as i32) % 255 / 2) as u8;
+        let g = (( crystal1.color.g as i32 + crystal2.color.g as i32) % 255 / 2) as u8;
+        let blue = (( crystal1.color.b as i32 + crystal2.color.b as i32) % 255 / 2) as u8;
         let color = ColorId::new(r, g, blue);
 
         let new_crystal = Crystal {
             effect: Effect {
                 level,
-                effect_type: effect_type.clone()
+                effect_type
             },
             phase,
             resonance,
@@ -129,7 +164,7 @@ impl Inventory {
 
 }
 
-#[derive(Default, PartialEq)]
+#[derive(Default, Clone, PartialEq)]
 pub struct Crystal {
     pub color: ColorId,
     pub effect: Effect,
@@ -149,6 +184,13 @@ impl ColorId {
         ColorId { r, g, b }
     }
 
+    /// Distance to `other` in RGB space, from 0 for the same color to 1 for black and white.
+    pub fn distance(&self, other: &ColorId) -> f32 {
+        let a = Vec3::new(self.r as f32, self.g as f32, self.b as f32);
+        let b = Vec3::new(other.r as f32, other.g as f32, other.b as f32);
+        a.distance(b) / (255. * 3f32.sqrt())
+    }
+
     pub fn to_bevy(&self) -> bevy::prelude::Color {
         Color::srgb_u8(self.r, self.g, self.b)
     }
@@ -185,10 +227,6 @@ impl ColorId {
     }
 }
 
-struct ActiveEffects {
-    pub effects: Vec<Effect>,
-}
-
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
@@ -201,6 +239,8 @@ impl Plugin for PlayerPlugin {
                 handle_mouse,
                 smooth_camera_follow,
                 update_charging_arrow,
+                draw_trajectory_preview.after(update_charging_arrow),
+                handle_bash,
             )
                 .run_if(in_state(AppState::InGame)),
         )
@@ -251,7 +291,7 @@ pub fn spawn_player(
         Collider::convex_hull(
             &regular_polygon_vertices(30., 3)
                 .iter()
-                .map(|e| Vect::from(e.clone()))
+                .map(|e| Vect::from(*e))
                 .collect::<Vec<Vect>>(),
         )
         .unwrap(),
@@ -263,6 +303,7 @@ pub fn spawn_player(
         },
         Restitution::coefficient(1.0),
         ExternalImpulse::default(),
+        CollisionLayer::Player.bundle(),
         RenderLayers::layer(0),
         FirstPass,
     ));
@@ -315,6 +356,38 @@ pub fn spawn_player(
             }
         });
 
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(60.0),
+            left: Val::Px(15.0),
+            ..default()
+        },
+        TextFont {
+            font: assets.load("Kenneymini.ttf"),
+            font_size: 30.0,
+            ..default()
+        },
+        AmmoCounter,
+    ));
+
+    commands.spawn((
+        Text::new("Crystal: none"),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(100.0),
+            left: Val::Px(15.0),
+            ..default()
+        },
+        TextFont {
+            font: assets.load("Kenneymini.ttf"),
+            font_size: 20.0,
+            ..default()
+        },
+        EquippedCrystalText,
+    ));
+
     commands
         .spawn((
             Node {
@@ -386,7 +459,7 @@ fn update_bow_position(
 fn update_charging_arrow(
     windows: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform), With<FirstPass>>,
-    mut arrow_query: Query<(&mut Transform, &mut ChargingArrow)>,
+    mut arrow_query: Query<(&mut Transform, &mut ChargingArrow, &mut Sprite)>,
     player_query: Query<&Transform, (With<Player>, Without<ChargingArrow>)>,
     time: Res<Time>,
     mut camera_shake: ResMut<ScreenShake>,
@@ -395,9 +468,10 @@ fn update_charging_arrow(
         return;
     }
     let mut arrow = arrow_query.single_mut().unwrap();
-    if arrow.1.charge_time < 2. {
+    if arrow.1.charge_time < MAX_CHARGE {
         arrow.1.charge_time += time.delta_secs();
     }
+    arrow.2.color = ChargeState::from_charge(arrow.1.charge_time).color();
 
     let player = player_query.single().unwrap();
 
@@ -410,12 +484,11 @@ fn update_charging_arrow(
             let alpha = f32::atan2(
                 world_pos.y - player.translation.y,
                 world_pos.x - player.translation.x,
-            );
+            ) + aim_wobble(arrow.1.charge_time, time.elapsed_secs());
+            let draw = arrow.1.charge_time.min(FULL_CHARGE) * 5.;
 
-            let x =
-                player.translation.x + (BOW_OFFSET - arrow.1.charge_time * 5.) * f32::cos(alpha);
-            let y =
-                player.translation.y + (BOW_OFFSET - arrow.1.charge_time * 5.) * f32::sin(alpha);
+            let x = player.translation.x + (BOW_OFFSET - draw) * f32::cos(alpha);
+            let y = player.translation.y + (BOW_OFFSET - draw) * f32::sin(alpha);
             camera_shake.trauma = arrow.1.charge_time * 0.2;
 
             arrow.0.translation.x = x;
@@ -426,6 +499,7 @@ fn update_charging_arrow(
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn handle_mouse(
     windows: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform), With<FirstPass>>,
@@ -433,14 +507,19 @@ fn handle_mouse(
     mouse: Res<ButtonInput<MouseButton>>,
     assets: Res<AssetServer>,
     mut player_query: Query<(&Transform, &mut Velocity), (With<Player>, Without<Bow>)>,
+    charging_query: Query<&Transform, With<ChargingArrow>>,
     mut bow_state: Local<BowState>,
+    mut shake: ResMut<ScreenShake>,
     time: Res<Time>,
     particle_handles: Res<ParticleHandles>,
+    mut quiver: ResMut<Quiver>,
+    upgrades: Res<Upgrades>,
+    inventory: Res<Inventory>,
 ) {
     let window = windows.single().unwrap();
     let mut player = player_query.single_mut().unwrap();
 
-    if mouse.just_pressed(MouseButton::Left) {
+    if mouse.just_pressed(MouseButton::Left) && quiver.current > 0 {
         bow_state.charging = true;
         bow_state.charge_time = 0.0;
         let mut id = None;
@@ -456,6 +535,7 @@ fn handle_mouse(
 
                 let arrow = assets.load("arrow.png");
 
+                quiver.current -= 1;
                 id = Some(
                     commands
                         .spawn((
@@ -484,48 +564,67 @@ fn handle_mouse(
         bow_state.charging_arrow = id;
     }
 
-    if bow_state.charging && mouse.pressed(MouseButton::Left) && bow_state.charge_time < 2. {
+    if bow_state.charging && mouse.pressed(MouseButton::Left) && bow_state.charge_time < MAX_CHARGE {
         bow_state.charge_time += time.delta_secs();
     }
 
     if bow_state.charging && mouse.just_released(MouseButton::Left) {
         bow_state.charging = false;
-        if let Some(cursor_pos) = window.cursor_position() {
-            let (camera, camera_transform) = camera_q.single().unwrap();
-
-            if let Ok(world_pos) = camera.viewport_to_world_2d(camera_transform, cursor_pos * SCALE)
-            {
-                let alpha = f32::atan2(
-                    world_pos.y - player.0.translation.y,
-                    world_pos.x - player.0.translation.x,
-                );
-
-                commands
-                    .entity(bow_state.charging_arrow.unwrap())
-                    .insert((
-                        Arrow {
-                            damage: bow_state.charge_time,
-                        },
-                        ActiveEvents::COLLISION_EVENTS,
-                        Velocity {
-                            linvel: Vect::new(
-                                (ARROW_SPEED * f32::cos(alpha) * bow_state.charge_time)
-                                    .clamp(-MAX_ARROW_SPEED, MAX_ARROW_SPEED),
-                                (ARROW_SPEED * f32::sin(alpha) * bow_state.charge_time)
-                                    .clamp(-MAX_ARROW_SPEED, MAX_ARROW_SPEED),
-                            ),
+        if let Some(charging_arrow) = bow_state.charging_arrow
+            && let Ok(arrow_tr) = charging_query.get(charging_arrow)
+        {
+            // the charging arrow already carries the cursor angle plus any overcharge wobble
+            let aim = (arrow_tr.rotation * Vec3::X).truncate().to_angle();
+            let power = launch_power(bow_state.charge_time);
+            let (damage, crit) = arrow_damage(bow_state.charge_time);
+            let spread = aim_spread(
+                bow_state.charge_time,
+                player.1.linvel.length(),
+                upgrades.focus(&inventory),
+            );
+            let count = upgrades.arrow_count(&inventory).min(quiver.current + 1);
+            let mut rng = rand::rng();
+
+            for (i, angle) in fan_angles(aim, count).enumerate() {
+                let alpha = angle + rng.random_range(-spread..=spread);
+                let transform = Transform {
+                    rotation: Quat::from_rotation_z(alpha),
+                    ..*arrow_tr
+                };
+                let arrow = Arrow {
+                    damage,
+                    crit,
+                    effect: inventory.equipped.as_ref().map(|crystal| crystal.effect.clone()),
+                    color: inventory.equipped.as_ref().map(|crystal| crystal.color),
+                };
+                let arrow = loosed_arrow(alpha, power, arrow, &particle_handles);
+                if i == 0 {
+                    commands
+                        .entity(charging_arrow)
+                        .insert((transform, arrow))
+                        .remove::<ChargingArrow>();
+                } else {
+                    quiver.current -= 1;
+                    commands.spawn((
+                        Sprite {
+                            image: assets.load("arrow.png"),
+                            color: ChargeState::from_charge(bow_state.charge_time).color(),
                             ..default()
                         },
-                        RigidBody::Dynamic,
-                        ParticleEffect::new(particle_handles.arrow_trail.clone()),
-                        Collider::cuboid(27.0, 3.15),
-                        Restitution::coefficient(1.),
-                        RenderLayers::layer(0),
-                        FirstPass,
-                    ))
-                    .remove::<ChargingArrow>();
-                player.1.linvel.x -= f32::cos(alpha) * KNOCKBACK * bow_state.charge_time;
-                player.1.linvel.y -= f32::sin(alpha) * KNOCKBACK * bow_state.charge_time;
+                        transform,
+                        arrow,
+                    ));
+                }
+            }
+            player.1.linvel.x -= f32::cos(aim) * KNOCKBACK * power;
+            player.1.linvel.y -= f32::sin(aim) * KNOCKBACK * power;
+
+            if crit {
+                shake.trauma = 1.5;
+                commands.spawn((
+                    ParticleEffect::new(particle_handles.crit_flash.clone()),
+                    Transform::from_translation(arrow_tr.translation),
+                ));
             }
         }
 
@@ -533,24 +632,168 @@ fn handle_mouse(
     }
 }
 
+/// Swings the bow in front of the player, hurting and shoving every enemy in the arc.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn handle_bash(
+    mouse: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+    mut cooldown: Local<f32>,
+    player_query: Query<&Transform, With<Player>>,
+    bow_query: Query<&Transform, (With<Bow>, Without<Player>)>,
+    enemy_query: Query<(Entity, &Transform), (With<Enemy>, Without<Player>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut commands: Commands,
+    particle_handles: Res<ParticleHandles>,
+    sfx: Res<SFX>,
+) {
+    *cooldown -= time.delta_secs();
+    if !mouse.just_pressed(MouseButton::Right) || *cooldown > 0. {
+        return;
+    }
+    *cooldown = BASH_COOLDOWN;
+
+    let player = player_query.single().unwrap();
+    let bow = bow_query.single().unwrap();
+    let origin = player.translation.truncate();
+    let facing = (bow.translation.truncate() - origin).normalize_or_zero();
+
+    commands.spawn((
+        ParticleEffect::new(particle_handles.bash_swing.clone()),
+        Transform::from_translation(bow.translation),
+    ));
+    commands.spawn(AudioPlayer(sfx.swing.clone()));
+
+    for (entity, enemy_tr) in &enemy_query {
+        let offset = enemy_tr.translation.truncate() - origin;
+        if offset.length() > BASH_RANGE || facing.angle_to(offset).abs() > BASH_ARC {
+            continue;
+        }
+        damage_events.write(DamageEvent {
+            target: entity,
+            source: DamageSource::Bash,
+            attacker: None,
+            damage_type: DamageType::Physical,
+            color: None,
+            amount: BASH_DAMAGE,
+            crit: false,
+            knockback: offset.normalize_or_zero() * BASH_KNOCKBACK,
+            position: enemy_tr.translation.truncate(),
+        });
+    }
+}
+
+fn loosed_arrow(
+    alpha: f32,
+    power: f32,
+    arrow: Arrow,
+    particle_handles: &ParticleHandles,
+) -> impl Bundle {
+    (
+        arrow,
+        ActiveEvents::COLLISION_EVENTS,
+        Velocity {
+            linvel: arrow_velocity(alpha, power),
+            ..default()
+        },
+        RigidBody::Dynamic,
+        ParticleEffect::new(particle_handles.arrow_trail.clone()),
+        Collider::cuboid(ARROW_HALF_EXTENTS.x, ARROW_HALF_EXTENTS.y),
+        CollisionLayer::Arrow.bundle(),
+        Restitution::coefficient(1.),
+        Damping {
+            linear_damping: ARROW_DAMPING,
+            angular_damping: 0.,
+        },
+        RenderLayers::layer(0),
+        FirstPass,
+    )
+}
+
+/// Shape-casts the charging arrow's collider along its launch velocity and
+/// draws the path as dots, following the first few ricochets off planets.
+fn draw_trajectory_preview(
+    charging_query: Query<(&Transform, &ChargingArrow)>,
+    planet_query: Query<(), With<Planet>>,
+    rapier_context: ReadRapierContext,
+    mut gizmos: Gizmos,
+) {
+    let Ok((arrow_tr, charging)) = charging_query.single() else {
+        return;
+    };
+    let Ok(context) = rapier_context.single() else {
+        return;
+    };
+    let alpha = (arrow_tr.rotation * Vec3::X).truncate().to_angle();
+    let velocity = arrow_velocity(alpha, launch_power(charging.charge_time));
+    let color = ChargeState::from_charge(charging.charge_time).color().with_alpha(0.6);
+    let shape = Collider::cuboid(ARROW_HALF_EXTENTS.x, ARROW_HALF_EXTENTS.y);
+    let filter = QueryFilter::default().groups(CollisionLayer::Arrow.collision_groups()).exclude_sensors();
+
+    let total = arrow_travel_distance(velocity.length());
+    let mut remaining = total;
+    let mut pos = arrow_tr.translation.truncate();
+    let mut dir = velocity.normalize_or_zero();
+    // carried across segments so the dots stay evenly spaced around bounces
+    let mut travelled = 0.;
+
+    for _ in 0..=PREVIEW_BOUNCES {
+        if remaining <= 0. || dir == Vec2::ZERO {
+            break;
+        }
+        let options = ShapeCastOptions {
+            max_time_of_impact: remaining,
+            target_distance: 0.,
+            stop_at_penetration: false,
+            compute_impact_geometry_on_penetration: true,
+        };
+        let hit = context.cast_shape(pos, dir.to_angle(), dir, &shape, options, filter);
+        let length = hit.map_or(remaining, |(_, hit)| hit.time_of_impact);
+        let end = pos + dir * length;
+
+        let mut d = PREVIEW_DOT_SPACING - travelled % PREVIEW_DOT_SPACING;
+        while d < length {
+            let fade = 1. - (total - remaining + d) / total;
+            gizmos.circle_2d(pos + dir * d, 1.5, color.with_alpha(0.6 * fade));
+            d += PREVIEW_DOT_SPACING;
+        }
+        travelled += length;
+        remaining -= length;
+
+        let Some((entity, hit)) = hit else {
+            break;
+        };
+        // enemies and portals stop the arrow, only planets are bounced off
+        if !planet_query.contains(entity) {
+            gizmos.circle_2d(end, 8., color);
+            break;
+        }
+        let Some(details) = hit.details else {
+            break;
+        };
+        let normal = details.normal1.normalize_or_zero();
+        dir = (dir - 2. * dir.dot(normal) * normal).normalize_or_zero();
+        pos = end;
+    }
+}
+
 fn smooth_camera_follow(
     time: Res<Time>,
     player_query: Query<&Transform, With<Player>>,
     mut camera_query: Query<&mut Transform, (With<Name>, Without<Player>)>,
 ) {
-    if let Ok(player_transform) = player_query.single() {
-        if let Ok(mut camera_transform) = camera_query.single_mut() {
-            let smoothness = 5.0;
+    if let Ok(player_transform) = player_query.single()
+        && let Ok(mut camera_transform) = camera_query.single_mut()
+    {
+        let smoothness = 5.0;
 
-            let current = camera_transform.translation;
+        let current = camera_transform.translation;
 
-            let target = Vec3::new(
-                player_transform.translation.x,
-                player_transform.translation.y,
-                current.z,
-            );
+        let target = Vec3::new(
+            player_transform.translation.x,
+            player_transform.translation.y,
+            current.z,
+        );
 
-            camera_transform.translation = current.lerp(target, time.delta_secs() * smoothness);
-        }
+        camera_transform.translation = current.lerp(target, time.delta_secs() * smoothness);
     }
 }
@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_hanabi::ParticleEffect;
+use bevy_rapier2d::prelude::*;
+
+use crate::damage::{DamageEvent, DamageSource, DamageSystems, DamageType};
+use crate::enemy::{calculate_intercept_point, Enemy};
+use crate::global::{CircleCollider, ENEMY_COLOR};
+use crate::particles::ParticleHandles;
+use crate::physics::{started_collision, CollisionLayer};
+use crate::player::Player;
+use crate::sfx::SFX;
+use crate::{AppState, FirstPass};
+
+pub const PROJECTILE_SPEED: f32 = 220.0;
+const PROJECTILE_RADIUS: f32 = 5.0;
+const PROJECTILE_DAMAGE: f32 = 1.0;
+const PROJECTILE_KNOCKBACK: f32 = 60.0;
+const PROJECTILE_LIFETIME: f32 = 4.0;
+/// Gap between the shooter's hull and a freshly fired projectile.
+pub const MUZZLE_OFFSET: f32 = 6.0;
+const FIRE_RANGE: f32 = 420.0;
+const FIRE_COOLDOWN: f32 = 2.5;
+/// Angle between two shots of one volley.
+const VOLLEY_SPREAD: f32 = 0.25;
+
+/// Enemy that periodically fires at the player.
+#[derive(Component)]
+pub struct Shooter {
+    cooldown: f32,
+    /// Shots fired at once, fanned around the aim direction.
+    volley: u32,
+}
+
+impl Default for Shooter {
+    fn default() -> Self {
+        Shooter::volley(1)
+    }
+}
+
+impl Shooter {
+    pub fn volley(volley: u32) -> Self {
+        Shooter { cooldown: FIRE_COOLDOWN, volley: volley.max(1) }
+    }
+}
+
+#[derive(Component)]
+pub struct EnemyProjectile {
+    lifetime: f32,
+    owner: Entity,
+}
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                fire_at_player,
+                projectile_hits.before(DamageSystems::Resolve),
+                expire_projectiles,
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fire_at_player(
+    shooter_query: Query<(Entity, &Transform, &CircleCollider, &mut Shooter), With<Enemy>>,
+    player_query: Query<(&Transform, &Velocity), With<Player>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    particle_handles: Res<ParticleHandles>,
+    sfx: Res<SFX>,
+    time: Res<Time>,
+) {
+    let (player_tr, player_vel) = player_query.single().unwrap();
+    let target = player_tr.translation.truncate();
+
+    for (owner, transform, collider, mut shooter) in shooter_query {
+        shooter.cooldown -= time.delta_secs();
+        let origin = transform.translation.truncate();
+        if shooter.cooldown > 0. || origin.distance(target) > FIRE_RANGE {
+            continue;
+        }
+        shooter.cooldown = FIRE_COOLDOWN;
+
+        let aim = calculate_intercept_point(origin, target, player_vel.linvel, PROJECTILE_SPEED).unwrap_or(target);
+        let aim_dir = (aim - origin).normalize_or_zero();
+        for i in 0..shooter.volley {
+            let offset = (i as f32 - (shooter.volley - 1) as f32 / 2.) * VOLLEY_SPREAD;
+            let dir = Vec2::from_angle(offset).rotate(aim_dir);
+            spawn_projectile(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &particle_handles,
+                owner,
+                origin + dir * (collider.0 + MUZZLE_OFFSET),
+                dir,
+            );
+        }
+        commands.spawn(AudioPlayer(sfx.shoot.clone()));
+    }
+}
+
+/// Fires one enemy shot from `position` along `dir`.
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    particle_handles: &Res<ParticleHandles>,
+    owner: Entity,
+    position: Vec2,
+    dir: Vec2,
+) {
+    commands.spawn((
+        EnemyProjectile { lifetime: PROJECTILE_LIFETIME, owner },
+        Mesh2d(meshes.add(Circle::new(PROJECTILE_RADIUS))),
+        MeshMaterial2d(materials.add(ColorMaterial::from_color(ENEMY_COLOR))),
+        Transform::from_translation(position.extend(0.)),
+        RigidBody::KinematicVelocityBased,
+        Velocity::linear(dir * PROJECTILE_SPEED),
+        Collider::ball(PROJECTILE_RADIUS),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        CollisionLayer::EnemyProjectile.bundle(),
+        ParticleEffect::new(particle_handles.enemy_projectile.clone()),
+        RenderLayers::layer(0),
+        FirstPass,
+    ));
+}
+
+fn projectile_hits(
+    mut collision_events: EventReader<CollisionEvent>,
+    layer_query: Query<&CollisionLayer>,
+    projectile_query: Query<(&EnemyProjectile, &Transform, &Velocity)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut commands: Commands,
+    particle_handles: Res<ParticleHandles>,
+) {
+    for event in collision_events.read() {
+        let Some((projectile, target, target_layer)) = started_collision(event, &layer_query, CollisionLayer::EnemyProjectile) else {
+            continue;
+        };
+        let Ok((shot, transform, velocity)) = projectile_query.get(projectile) else {
+            continue;
+        };
+        // planets just swallow the shot
+        if target_layer == CollisionLayer::Player {
+            damage_events.write(DamageEvent {
+                target,
+                source: DamageSource::EnemyProjectile,
+                attacker: Some(shot.owner),
+                damage_type: DamageType::Physical,
+                color: None,
+                amount: PROJECTILE_DAMAGE,
+                crit: false,
+                knockback: velocity.linvel.normalize_or_zero() * PROJECTILE_KNOCKBACK,
+                position: transform.translation.truncate(),
+            });
+        }
+        commands.entity(projectile).despawn();
+        commands.spawn((
+            ParticleEffect::new(particle_handles.enemy_damage.clone()),
+            Transform::from_translation(transform.translation),
+        ));
+    }
+}
+
+fn expire_projectiles(
+    projectile_query: Query<(Entity, &mut EnemyProjectile)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut projectile) in projectile_query {
+        projectile.lifetime -= time.delta_secs();
+        if projectile.lifetime <= 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
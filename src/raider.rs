@@ -0,0 +1,286 @@
+use std::f32::consts::TAU;
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use rand::prelude::*;
+
+use crate::behaviour::Action;
+use crate::damage::{DamageDealt, DamageEvent, DamageSource, DamageSystems, DamageType};
+use crate::enemy::{spawn_enemy, Enemy, HealthBar, HealthBarOwner};
+use crate::global::{adjusted_glow, CircleCollider};
+use crate::planets::Planet;
+use crate::player::{Crystal, Inventory, Player};
+use crate::sfx::SFX;
+use crate::world::{is_position_safe, roll_sides, Difficulty, EnemiesCounter, PlanetData, PLANET_SCALE};
+use crate::{AppState, FirstPass, GLOW_FACTOR};
+
+const RAIDER_INTERVAL: f32 = 30.0;
+const MAX_RAIDERS: usize = 4;
+/// Raiders show up next to a planet, but never in front of the player.
+const MIN_PLAYER_DISTANCE: f32 = 800.0;
+const SPAWN_GAP: f32 = 250.0;
+/// Raiders hit a planet once they're this close to its edge.
+const RAID_RANGE: f32 = 30.0;
+const RAID_DAMAGE: f32 = 1.0;
+const RAID_COOLDOWN: f32 = 1.0;
+/// Thieves that get this far from the player are gone, with their loot.
+const ESCAPE_DISTANCE: f32 = 1600.0;
+const RAIDER_COLOR: Color = Color::srgb(1., 0.7, 0.1);
+const LOOT_SIZE: f32 = 10.0;
+/// How close the player has to get to a dropped crystal to take it.
+const LOOT_PICKUP_RADIUS: f32 = 20.0;
+
+/// Enemy that goes for planets instead of the player, stealing their crystals.
+#[derive(Component)]
+pub struct Raider {
+    target: Option<Entity>,
+    /// Where the target planet is.
+    destination: Vec2,
+    cooldown: f32,
+    loot: Option<Crystal>,
+}
+
+impl Raider {
+    /// Planet the raider is heading for, while it has nothing stolen yet.
+    pub fn target(&self) -> Option<Entity> {
+        self.target.filter(|_| self.loot.is_none())
+    }
+
+    pub fn escaping(&self) -> bool {
+        self.loot.is_some()
+    }
+
+    /// Movement replacing the brain's own, heading for the target or running off with the loot.
+    pub fn action(&self) -> Option<Action> {
+        if self.escaping() {
+            Some(Action::Flee)
+        } else {
+            self.target.map(|_| Action::Approach { target: self.destination })
+        }
+    }
+
+    /// Takes the crystal of a planet the raider just destroyed.
+    pub fn steal(&mut self, crystal: Crystal) {
+        self.loot = Some(crystal);
+        self.target = None;
+    }
+}
+
+pub struct RaiderPlugin;
+
+impl Plugin for RaiderPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                spawn_raiders,
+                pick_raid_targets,
+                raid_planets.before(DamageSystems::Resolve),
+                show_loot,
+                escape_with_loot,
+                drop_loot.in_set(DamageSystems::React),
+                collect_loot,
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_raiders(
+    raider_query: Query<(), With<Raider>>,
+    player_query: Query<&Transform, With<Player>>,
+    planet_data: Res<PlanetData>,
+    difficulty: Res<Difficulty>,
+    mut enemies: ResMut<EnemiesCounter>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut timer: Local<f32>,
+    time: Res<Time>,
+) {
+    *timer += time.delta_secs();
+    if *timer < RAIDER_INTERVAL || raider_query.iter().count() >= MAX_RAIDERS {
+        return;
+    }
+    let player = player_query.single().unwrap().translation.truncate();
+    let mut rng = rand::rng();
+    let Some(position) = (0..20)
+        .filter_map(|_| {
+            let (planet, radius, _) = planet_data.0.choose(&mut rng)?;
+            Some(*planet + Vec2::from_angle(rng.random_range(0.0..TAU)) * (radius * PLANET_SCALE + SPAWN_GAP))
+        })
+        .find(|position| position.distance(player) > MIN_PLAYER_DISTANCE && is_position_safe(*position, &planet_data))
+    else {
+        return;
+    };
+    *timer = 0.;
+
+    info!("Raider spawned at {}.", position);
+    let enemy = Enemy::new(roll_sides(&mut rng, difficulty.0));
+    let id = spawn_enemy(&mut commands, &mut meshes, &mut materials, &mut enemies, enemy, position);
+    commands.entity(id).insert((
+        Raider { target: None, destination: position, cooldown: RAID_COOLDOWN, loot: None },
+        MeshMaterial2d(materials.add(ColorMaterial::from_color(adjusted_glow(RAIDER_COLOR, GLOW_FACTOR)))),
+    ));
+}
+
+/// Sends idle raiders to the closest planet still standing.
+fn pick_raid_targets(
+    raider_query: Query<(&Transform, &mut Raider)>,
+    planet_query: Query<&Transform, (With<Planet>, Without<Raider>)>,
+    planet_data: Res<PlanetData>,
+) {
+    for (transform, mut raider) in raider_query {
+        if raider.escaping() {
+            continue;
+        }
+        if raider.target.is_none_or(|planet| planet_query.get(planet).is_err()) {
+            let position = transform.translation.truncate();
+            raider.target = planet_data
+                .0
+                .iter()
+                .min_by(|a, b| a.0.distance(position).total_cmp(&b.0.distance(position)))
+                .map(|(_, _, planet)| *planet);
+        }
+        // planets drift when they get knocked around
+        if let Some(planet_tr) = raider.target.and_then(|planet| planet_query.get(planet).ok()) {
+            raider.destination = planet_tr.translation.truncate();
+        }
+    }
+}
+
+fn raid_planets(
+    raider_query: Query<(Entity, &Transform, &CircleCollider, &mut Raider)>,
+    planet_query: Query<&Transform, With<Planet>>,
+    planet_data: Res<PlanetData>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    for (entity, transform, collider, mut raider) in raider_query {
+        raider.cooldown -= time.delta_secs();
+        let Some(target) = raider.target() else {
+            continue;
+        };
+        let (Ok(planet_tr), Some((_, radius, _))) = (planet_query.get(target), planet_data.0.iter().find(|(_, _, planet)| *planet == target)) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        let planet = planet_tr.translation.truncate();
+        if raider.cooldown > 0. || position.distance(planet) > radius * PLANET_SCALE + collider.0 + RAID_RANGE {
+            continue;
+        }
+        raider.cooldown = RAID_COOLDOWN;
+        damage_events.write(DamageEvent {
+            target,
+            source: DamageSource::Raid,
+            attacker: Some(entity),
+            damage_type: DamageType::Physical,
+            color: None,
+            amount: RAID_DAMAGE,
+            crit: false,
+            knockback: Vec2::ZERO,
+            position: planet + (position - planet).normalize_or_zero() * radius * PLANET_SCALE,
+        });
+    }
+}
+
+/// Stolen crystal, carried in the middle of the thief.
+#[derive(Component)]
+struct Loot;
+
+/// Crystal a thief dropped, lying where it died until the player takes it.
+#[derive(Component)]
+struct LootPickup(Crystal);
+
+fn loot_bundle(
+    crystal: &Crystal,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) -> (Mesh2d, MeshMaterial2d<ColorMaterial>, RenderLayers, FirstPass) {
+    (
+        Mesh2d(meshes.add(RegularPolygon::new(LOOT_SIZE, 4))),
+        MeshMaterial2d(materials.add(ColorMaterial::from_color(adjusted_glow(crystal.color.to_bevy(), GLOW_FACTOR)))),
+        RenderLayers::layer(0),
+        FirstPass,
+    )
+}
+
+fn show_loot(
+    raider_query: Query<(Entity, &Raider, Option<&Children>), Changed<Raider>>,
+    loot_query: Query<(), With<Loot>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, raider, children) in raider_query {
+        let Some(crystal) = &raider.loot else {
+            continue;
+        };
+        if children.is_some_and(|children| children.iter().any(|child| loot_query.get(child).is_ok())) {
+            continue;
+        }
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((Loot, loot_bundle(crystal, &mut meshes, &mut materials), Transform::from_xyz(0., 0., 0.1)));
+        });
+    }
+}
+
+/// Thieves that outran the player take the crystal with them.
+fn escape_with_loot(
+    raider_query: Query<(Entity, &Transform, &Raider)>,
+    player_query: Query<&Transform, With<Player>>,
+    health_bar_query: Query<(Entity, &HealthBarOwner), With<HealthBar>>,
+    mut enemies: ResMut<EnemiesCounter>,
+    mut commands: Commands,
+) {
+    let player = player_query.single().unwrap().translation.truncate();
+    for (entity, transform, raider) in raider_query {
+        if !raider.escaping() || transform.translation.truncate().distance(player) < ESCAPE_DISTANCE {
+            continue;
+        }
+        info!("A raider escaped with a crystal.");
+        commands.entity(entity).despawn();
+        for (bar, owner) in &health_bar_query {
+            if owner.0 == entity {
+                commands.entity(bar).despawn();
+            }
+        }
+        enemies.0 -= 1;
+    }
+}
+
+/// Killing a thief drops its crystal where it died.
+fn drop_loot(
+    mut dealt: EventReader<DamageDealt>,
+    mut raider_query: Query<(&mut Raider, &Transform)>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in dealt.read() {
+        if !event.killed {
+            continue;
+        }
+        if let Ok((mut raider, transform)) = raider_query.get_mut(event.target)
+            && let Some(crystal) = raider.loot.take()
+        {
+            let bundle = loot_bundle(&crystal, &mut meshes, &mut materials);
+            commands.spawn((LootPickup(crystal), bundle, Transform::from_translation(transform.translation)));
+        }
+    }
+}
+
+fn collect_loot(
+    player_query: Query<(&Transform, &CircleCollider), With<Player>>,
+    pickup_query: Query<(Entity, &Transform, &LootPickup)>,
+    mut inventory: ResMut<Inventory>,
+    mut commands: Commands,
+    sfx: Res<SFX>,
+) {
+    let (player, collider) = player_query.single().unwrap();
+    for (entity, transform, pickup) in pickup_query {
+        if transform.translation.truncate().distance(player.translation.truncate()) < collider.0 + LOOT_PICKUP_RADIUS {
+            info!("Recovered a stolen {:?} crystal.", pickup.0.effect.effect_type);
+            commands.entity(entity).despawn();
+            inventory.crystals.push(pickup.0.clone());
+            commands.spawn(AudioPlayer(sfx.levelup.clone()));
+        }
+    }
+}
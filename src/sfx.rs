@@ -7,6 +7,10 @@ pub struct SFX {
     pub levelup: Handle<AudioSource>,
     pub sell: Handle<AudioSource>,
     pub combine: Handle<AudioSource>,
+    pub swing: Handle<AudioSource>,
+    pub shoot: Handle<AudioSource>,
+    pub spawn: Handle<AudioSource>,
+    pub portal: Handle<AudioSource>,
 }
 
 pub struct SFXPlugin;
@@ -29,5 +33,9 @@ fn setup_sfx(
     sfx.levelup = asset_server.load("levelup.wav");
     sfx.sell = asset_server.load("sell.wav");
     sfx.combine = asset_server.load("combine.wav");
+    sfx.swing = asset_server.load("swing.wav");
+    sfx.shoot = asset_server.load("shoot.wav");
+    sfx.spawn = asset_server.load("spawn.wav");
+    sfx.portal = asset_server.load("portal.wav");
     info!("SFX loaded.");
 }
\ No newline at end of file
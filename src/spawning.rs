@@ -0,0 +1,334 @@
+use std::f32::consts::TAU;
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::view::RenderLayers;
+use bevy_hanabi::ParticleEffect;
+use bevy_rapier2d::prelude::*;
+use rand::prelude::*;
+
+use crate::damage::{DamageDealt, DamageSystems};
+use crate::elite::{make_elite, roll_modifiers, EliteModifier};
+use crate::enemy::{spawn_enemy, spawn_health_bar, Enemy, HealthBar, HealthBarOwner, HP};
+use crate::global::{adjusted_glow, regular_polygon_vertices, ScreenShake};
+use crate::nest::{Guard, Nest};
+use crate::particles::ParticleHandles;
+use crate::physics::CollisionLayer;
+use crate::player::Player;
+use crate::sfx::SFX;
+use crate::world::{is_position_safe, roll_sides, Difficulty, EnemiesCounter, PlanetData};
+use crate::xp::spawn_orbs;
+use crate::{AppState, FirstPass, ENEMY_COLOR, GLOW_FACTOR};
+
+/// Warning time before an enemy appears.
+const TELEGRAPH_DURATION: f32 = 1.0;
+const TELEGRAPH_SPIN: f32 = 3.0;
+const OUTLINE_WIDTH: f32 = 3.0;
+const PORTAL_INTERVAL: f32 = 45.0;
+const MAX_PORTALS: usize = 3;
+const PORTAL_MIN_DISTANCE: f32 = 450.0;
+const PORTAL_MAX_DISTANCE: f32 = 750.0;
+const PORTAL_RADIUS: f32 = 50.0;
+const PORTAL_SIDES: u32 = 8;
+const PORTAL_SPIN: f32 = 1.5;
+const PORTAL_HP: f32 = 25.0;
+const PORTAL_HP_PER_DIFFICULTY: f32 = 10.0;
+const PORTAL_COLOR: Color = Color::srgb(0.6, 0.2, 1.);
+const WAVE_INTERVAL: f32 = 10.0;
+const WAVE_SIZE: f32 = 2.0;
+const WAVE_SIZE_PER_DIFFICULTY: f32 = 1.0;
+const MAX_WAVE_SIZE: usize = 8;
+/// How far from the portal the enemies of a wave appear.
+const WAVE_SPREAD: f32 = 120.0;
+const PORTAL_XP: f64 = 60.0;
+
+/// Growing outline where an enemy is about to appear.
+#[derive(Component)]
+pub struct SpawnTelegraph {
+    enemy: Enemy,
+    modifiers: Vec<EliteModifier>,
+    /// Portal or nest that opened it, the enemy never shows up if that one is gone.
+    source: Option<Entity>,
+    timer: f32,
+}
+
+impl SpawnTelegraph {
+    pub fn source(&self) -> Option<Entity> {
+        self.source
+    }
+}
+
+#[derive(Component)]
+struct TelegraphOutline;
+
+/// Rift that keeps sending waves of enemies until it's shot down.
+#[derive(Component)]
+pub struct Portal {
+    cooldown: f32,
+}
+
+#[derive(Component)]
+struct PortalRing;
+
+pub struct SpawningPlugin;
+
+impl Plugin for SpawningPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                hatch_telegraphs,
+                open_portals,
+                portal_waves,
+                spin_portal_rings,
+                destroy_portals.in_set(DamageSystems::Despawn),
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Ring between two regular polygons, `width` apart.
+pub fn polygon_outline(radius: f32, sides: usize, width: f32) -> Mesh {
+    let outer = regular_polygon_vertices(radius, sides);
+    let inner = regular_polygon_vertices(radius - width, sides);
+    let positions: Vec<[f32; 3]> = outer.iter().chain(inner.iter()).map(|v| [v.x, v.y, 0.]).collect();
+    let mut indices = Vec::new();
+    for i in 0..sides as u32 {
+        let j = (i + 1) % sides as u32;
+        let n = sides as u32;
+        indices.extend_from_slice(&[i, j, n + i, j, n + j, n + i]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; positions.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.5, 0.5]; positions.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+/// Announces `enemy` at `position`, it spawns once the outline has grown to its size.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_telegraph(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    particle_handles: &Res<ParticleHandles>,
+    enemy: Enemy,
+    modifiers: Vec<EliteModifier>,
+    position: Vec2,
+    source: Option<Entity>,
+) {
+    commands
+        .spawn((
+            SpawnTelegraph { enemy, modifiers, source, timer: TELEGRAPH_DURATION },
+            Transform::from_translation(position.extend(0.)),
+            Visibility::default(),
+            ParticleEffect::new(particle_handles.spawn_telegraph.clone()),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TelegraphOutline,
+                Mesh2d(meshes.add(polygon_outline(enemy.radius(), enemy.sides as usize, OUTLINE_WIDTH))),
+                MeshMaterial2d(materials.add(ColorMaterial::from_color(ENEMY_COLOR))),
+                Transform::from_scale(Vec3::splat(0.)),
+                RenderLayers::layer(0),
+                FirstPass,
+            ));
+        });
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn hatch_telegraphs(
+    telegraph_query: Query<(Entity, &mut SpawnTelegraph, &Transform, &Children)>,
+    mut outline_query: Query<&mut Transform, (With<TelegraphOutline>, Without<SpawnTelegraph>)>,
+    source_query: Query<(), Or<(With<Portal>, With<Nest>)>>,
+    nest_query: Query<(&Nest, &Transform), (Without<SpawnTelegraph>, Without<TelegraphOutline>)>,
+    mut enemies: ResMut<EnemiesCounter>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    particle_handles: Res<ParticleHandles>,
+    sfx: Res<SFX>,
+    time: Res<Time>,
+) {
+    for (entity, mut telegraph, transform, children) in telegraph_query {
+        telegraph.timer -= time.delta_secs();
+        let progress = 1. - (telegraph.timer / TELEGRAPH_DURATION).max(0.);
+        for &child in children {
+            if let Ok(mut outline) = outline_query.get_mut(child) {
+                outline.scale = Vec3::splat(progress);
+                outline.rotation = Quat::from_rotation_z(progress * TELEGRAPH_SPIN);
+            }
+        }
+        if telegraph.timer > 0. {
+            continue;
+        }
+
+        commands.entity(entity).try_despawn();
+        if telegraph.source.is_some_and(|source| source_query.get(source).is_err()) {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let enemy = telegraph.enemy;
+        let id = spawn_enemy(&mut commands, &mut meshes, &mut materials, &mut enemies, enemy, position);
+        let modifiers = std::mem::take(&mut telegraph.modifiers);
+        if !modifiers.is_empty() {
+            make_elite(&mut commands, &mut meshes, &mut materials, id, &enemy, modifiers);
+        }
+        if let Some(nest) = telegraph.source
+            && let Ok((nest_data, nest_tr)) = nest_query.get(nest)
+        {
+            commands.entity(id).insert(Guard { nest, home: nest_tr.translation.truncate(), radius: nest_data.patrol_radius() });
+        }
+        commands.spawn((
+            ParticleEffect::new(particle_handles.enemy_death.clone()),
+            Transform::from_translation(transform.translation),
+        ));
+        commands.spawn(AudioPlayer(sfx.spawn.clone()));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn open_portals(
+    portal_query: Query<(), With<Portal>>,
+    player_query: Query<&Transform, With<Player>>,
+    planet_data: Res<PlanetData>,
+    difficulty: Res<Difficulty>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    particle_handles: Res<ParticleHandles>,
+    sfx: Res<SFX>,
+    mut timer: Local<f32>,
+    time: Res<Time>,
+) {
+    *timer += time.delta_secs();
+    if *timer < PORTAL_INTERVAL || portal_query.iter().count() >= MAX_PORTALS {
+        return;
+    }
+    let player = player_query.single().unwrap().translation.truncate();
+    let mut rng = rand::rng();
+    let Some(position) = (0..20)
+        .map(|_| player + Vec2::from_angle(rng.random_range(0.0..TAU)) * rng.random_range(PORTAL_MIN_DISTANCE..PORTAL_MAX_DISTANCE))
+        .find(|position| is_position_safe(*position, &planet_data))
+    else {
+        return;
+    };
+    *timer = 0.;
+
+    info!("Opening a portal at {}.", position);
+    let hp = PORTAL_HP + PORTAL_HP_PER_DIFFICULTY * difficulty.0;
+    let color = adjusted_glow(PORTAL_COLOR, GLOW_FACTOR);
+    let id = commands
+        .spawn((
+            Portal { cooldown: WAVE_INTERVAL / 2. },
+            HP { current: hp, max: hp },
+            Mesh2d(meshes.add(RegularPolygon::new(PORTAL_RADIUS * 0.6, PORTAL_SIDES))),
+            MeshMaterial2d(materials.add(ColorMaterial::from_color(color))),
+            Transform::from_translation(position.extend(-0.5)),
+            RigidBody::Fixed,
+            Collider::ball(PORTAL_RADIUS * 0.6),
+            CollisionLayer::Portal.bundle(),
+            ParticleEffect::new(particle_handles.portal.clone()),
+            RenderLayers::layer(0),
+            FirstPass,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                PortalRing,
+                Mesh2d(meshes.add(polygon_outline(PORTAL_RADIUS, PORTAL_SIDES as usize, OUTLINE_WIDTH * 2.))),
+                MeshMaterial2d(materials.add(ColorMaterial::from_color(color))),
+                Transform::default(),
+                RenderLayers::layer(0),
+                FirstPass,
+            ));
+        })
+        .id();
+    spawn_health_bar(&mut commands, &mut meshes, &mut materials, id, position + Vec2::new(0., PORTAL_RADIUS + 15.));
+    commands.spawn(AudioPlayer(sfx.portal.clone()));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn portal_waves(
+    portal_query: Query<(Entity, &mut Portal, &Transform)>,
+    planet_data: Res<PlanetData>,
+    difficulty: Res<Difficulty>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    particle_handles: Res<ParticleHandles>,
+    time: Res<Time>,
+) {
+    let mut rng = rand::rng();
+    for (entity, mut portal, transform) in portal_query {
+        portal.cooldown -= time.delta_secs();
+        if portal.cooldown > 0. {
+            continue;
+        }
+        portal.cooldown = WAVE_INTERVAL;
+
+        let center = transform.translation.truncate();
+        let size = ((WAVE_SIZE + WAVE_SIZE_PER_DIFFICULTY * difficulty.0) as usize).min(MAX_WAVE_SIZE);
+        for i in 0..size {
+            let angle = TAU * i as f32 / size as f32 + rng.random_range(0.0..0.5);
+            let position = center + Vec2::from_angle(angle) * WAVE_SPREAD;
+            if !is_position_safe(position, &planet_data) {
+                continue;
+            }
+            let enemy = Enemy::new(roll_sides(&mut rng, difficulty.0));
+            let modifiers = roll_modifiers(&mut rng, &enemy, difficulty.0);
+            spawn_telegraph(&mut commands, &mut meshes, &mut materials, &particle_handles, enemy, modifiers, position, Some(entity));
+        }
+    }
+}
+
+fn spin_portal_rings(
+    ring_query: Query<&mut Transform, With<PortalRing>>,
+    time: Res<Time>,
+) {
+    for mut transform in ring_query {
+        transform.rotate_z(PORTAL_SPIN * time.delta_secs());
+    }
+}
+
+/// Closes a shot down portal, calling off whatever it was about to spawn.
+#[allow(clippy::too_many_arguments)]
+fn destroy_portals(
+    mut dealt: EventReader<DamageDealt>,
+    portal_query: Query<&Transform, With<Portal>>,
+    telegraph_query: Query<(Entity, &SpawnTelegraph)>,
+    health_bar_query: Query<(Entity, &HealthBarOwner), With<HealthBar>>,
+    mut commands: Commands,
+    mut shake: ResMut<ScreenShake>,
+    particle_handles: Res<ParticleHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    sfx: Res<SFX>,
+) {
+    for event in dealt.read() {
+        if !event.killed {
+            continue;
+        }
+        let Ok(transform) = portal_query.get(event.target) else {
+            continue;
+        };
+        info!("Portal destroyed.");
+        commands.entity(event.target).despawn();
+        for (bar, owner) in &health_bar_query {
+            if owner.0 == event.target {
+                commands.entity(bar).despawn();
+            }
+        }
+        for (telegraph, pending) in &telegraph_query {
+            if pending.source == Some(event.target) {
+                commands.entity(telegraph).try_despawn();
+            }
+        }
+        shake.trauma = shake.trauma.max(6.0);
+        commands.spawn((
+            ParticleEffect::new(particle_handles.crit_flash.clone()),
+            Transform::from_translation(transform.translation),
+        ));
+        commands.spawn(AudioPlayer(sfx.portal.clone()));
+        spawn_orbs(&mut commands, PORTAL_XP, transform.translation, &particle_handles, &mut meshes, &mut materials);
+    }
+}
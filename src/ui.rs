@@ -3,17 +3,18 @@ use std::collections::HashMap;
 
 use crate::AppState;
 use crate::global::UnwrapOrLogDefault;
-use crate::player::{Crystal, Inventory, PlayerHealth};
+use crate::player::{Inventory, PlayerHealth};
 use crate::sfx::SFX;
+use crate::arrow::Quiver;
+use crate::damage::{DamageDealt, DamageSystems, DamageTarget};
+use crate::upgrades::LevelUp;
 
 use bevy::color::palettes::css::{BLACK, WHITE};
-use bevy::window::{PrimaryMonitor, PrimaryWindow};
+use bevy::window::PrimaryWindow;
 use bevy::{color::palettes::css::RED, prelude::*};
 
 use bevy::color::palettes::basic::YELLOW;
-use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
-use bevy::picking::hover::HoverMap;
-use bevy::text::cosmic_text::ttf_parser::Weight::Black;
+use bevy::input::mouse::MouseWheel;
 use csv::Reader;
 use rand::Rng;
 use std::error::Error;
@@ -75,6 +76,7 @@ struct ColorEntry {
     name: String,
     hex: String,
     #[serde(rename = "good name")]
+    #[allow(dead_code)]
     good_name: Option<String>, // albo String jeśli zawsze coś tam jest
 }
 
@@ -95,6 +97,12 @@ pub struct HealthBarSegment {
 #[derive(Component)]
 pub struct PlayerHealthBar;
 
+#[derive(Component)]
+pub struct AmmoCounter;
+
+#[derive(Component)]
+pub struct EquippedCrystalText;
+
 #[derive(Component)]
 pub struct XPBar {
     pub level: i32,
@@ -136,7 +144,10 @@ impl Plugin for UIPlugin {
             (
                 update_health_bar_ui,
                 regenerate_healthbar,
+                track_player_damage.in_set(DamageSystems::React),
                 update_xp_bar,
+                update_ammo_counter,
+                update_equipped_crystal_text,
                 handle_keyboard,
                 handle_crystal_clicks,
                 handle_inventory_shortcuts,
@@ -179,7 +190,7 @@ fn update_health_bar_ui(
     if current_segment + 1 < player.num_segments {
         for i in (current_segment + 1)..player.num_segments {
             let (_, _, ent) = nodes_query
-                .get_mut(segments_map.get(&(i as usize)).unwrap().clone())
+                .get_mut(*segments_map.get(&(i as usize)).unwrap())
                 .unwrap();
 
             commands.entity(ent).despawn();
@@ -189,7 +200,7 @@ fn update_health_bar_ui(
 
     for i in 0..(current_segment) {
         let (mut current_node, mut color, _) = nodes_query
-            .get_mut(segments_map.get(&(i as usize)).unwrap().clone())
+            .get_mut(*segments_map.get(&(i as usize)).unwrap())
             .unwrap();
 
         current_node.width = Val::Px(100.0);
@@ -202,10 +213,9 @@ fn update_health_bar_ui(
 
     let (mut last_node, mut last_color, _) = nodes_query
         .get_mut(
-            segments_map
+            *segments_map
                 .get(&((current_segment) as usize))
-                .unwrap()
-                .clone(),
+                .unwrap(),
         )
         .unwrap();
 
@@ -217,6 +227,16 @@ fn update_health_bar_ui(
     last_color.0 = Color::Srgba(RED);
 }
 
+fn track_player_damage(
+    mut dealt: EventReader<DamageDealt>,
+    mut last_damage: ResMut<LastDamageTime>,
+    time: Res<Time>,
+) {
+    if dealt.read().any(|event| event.target_kind == DamageTarget::Player) {
+        last_damage.0 = time.elapsed_secs();
+    }
+}
+
 fn regenerate_healthbar(
     last_damage: Res<LastDamageTime>,
     time: Res<Time>,
@@ -234,7 +254,7 @@ fn regenerate_healthbar(
 fn handle_scrolling(
     mut mouse_wheel_events: EventReader<MouseWheel>,
     mut scroll_offset: ResMut<ScrollOffsetResource>,
-    mut frame_query: Query<(&GlobalTransform), With<CrystalFrame>>,
+    frame_query: Query<&GlobalTransform, With<CrystalFrame>>,
     window: Query<&Window, With<PrimaryWindow>>,
     inventory_visible: Res<InventoryVisible>,
 ) {
@@ -262,6 +282,7 @@ fn update_xp_bar(
     mut text_query: Query<&mut Text>,
     sfx: Res<SFX>,
     mut commands: Commands,
+    mut level_ups: EventWriter<LevelUp>,
 ) {
     let (mut xp_bar, mut node, children) = xp_query.single_mut().unwrap();
     let per_level = f32::exp2(xp_bar.level as f32) * 50.;
@@ -269,6 +290,7 @@ fn update_xp_bar(
         commands.spawn(AudioPlayer(sfx.levelup.clone()));
         xp_bar.level += 1;
         xp_bar.current = 0.;
+        level_ups.write(LevelUp(xp_bar.level));
     }
 
     for &child in children {
@@ -280,6 +302,34 @@ fn update_xp_bar(
     node.width = Val::Px(600. * (xp_bar.current / per_level));
 }
 
+fn update_ammo_counter(
+    mut counter_query: Query<&mut Text, With<AmmoCounter>>,
+    quiver: Res<Quiver>,
+) {
+    let mut text = counter_query.single_mut().unwrap();
+    text.0 = format!("Arrows: {}/{}", quiver.current, quiver.capacity);
+}
+
+fn update_equipped_crystal_text(
+    mut text_query: Query<(&mut Text, &mut TextColor), With<EquippedCrystalText>>,
+    inventory: Res<Inventory>,
+) {
+    if !inventory.is_changed() {
+        return;
+    }
+    let (mut text, mut color) = text_query.single_mut().unwrap();
+    match &inventory.equipped {
+        Some(crystal) => {
+            text.0 = format!("Crystal: {:?} {}", crystal.effect.effect_type, crystal.effect.level);
+            color.0 = crystal.color.to_bevy();
+        }
+        None => {
+            text.0 = "Crystal: none".to_string();
+            color.0 = Color::WHITE;
+        }
+    }
+}
+
 fn handle_keyboard(
     input: Res<ButtonInput<KeyCode>>,
     commands: Commands,
@@ -287,17 +337,16 @@ fn handle_keyboard(
     mut visible: ResMut<InventoryVisible>,
     inventory_query: Query<Entity, With<InventoryNode>>,
     mut time: ResMut<Time<Virtual>>,
-    window: Query<&Window, With<PrimaryWindow>>,
     asset_server: Res<AssetServer>,
 ) {
     if input.just_pressed(KeyCode::KeyE) {
         if !visible.0 {
             visible.0 = true;
-            spawn_inventory_ui(commands, inventory, window, asset_server);
+            spawn_inventory_ui(commands, inventory, asset_server);
             time.set_relative_speed(0.2);
         } else {
             visible.0 = false;
-            despawn_inventory_ui(commands, inventory, inventory_query);
+            despawn_inventory_ui(commands, inventory_query);
             time.set_relative_speed(1.);
         }
     }
@@ -305,7 +354,6 @@ fn handle_keyboard(
 
 fn despawn_inventory_ui(
     mut commands: Commands,
-    inventory: Res<Inventory>,
     inventory_query: Query<Entity, With<InventoryNode>>,
 ) {
     for node in inventory_query.iter() {
@@ -316,10 +364,8 @@ fn despawn_inventory_ui(
 fn spawn_inventory_ui(
     mut commands: Commands,
     inventory: Res<Inventory>,
-    window: Query<&Window, With<PrimaryWindow>>,
     asset_server: Res<AssetServer>,
 ) {
-    let window = window.single().unwrap();
     let mut crystals = Vec::new();
     for i in 1..=3 {
         let image = asset_server.load(format!("crystal{}.png", i));
@@ -356,8 +402,7 @@ fn spawn_inventory_ui(
                     BackgroundColor(Color::from(YELLOW)),
                 ))
                 .with_children(|row| {
-                    let mut i = 0;
-                    for crystal in &inventory.crystals {
+                    for (i, crystal) in inventory.crystals.iter().enumerate() {
                         row.spawn((
                             Node {
                                 width: Val::Px(100.),
@@ -388,7 +433,6 @@ fn spawn_inventory_ui(
                             BackgroundColor(WHITE.into()),
                             Button,
                         ));
-                        i += 1;
                     }
                 });
         });
@@ -403,7 +447,7 @@ fn spawn_inventory_ui(
                 ..default()
             },
             ZIndex(10),
-            BackgroundColor(Color::linear_rgba(0.0, 0.0, 0.0, 0.7).into()),
+            BackgroundColor(Color::linear_rgba(0.0, 0.0, 0.0, 0.7)),
             TooltipNode,
             Visibility::Hidden,
             InventoryNode,
@@ -421,6 +465,7 @@ fn spawn_inventory_ui(
         });
 }
 
+#[allow(clippy::type_complexity)]
 fn update_crystal_tooltip(
     mut tooltip_query: Query<(&mut Text, &ChildOf), With<TooltipText>>,
     mut tooltip_node: Query<(&mut Visibility, &mut Node), With<TooltipNode>>,
@@ -460,21 +505,20 @@ fn update_crystal_tooltip(
 
 fn handle_crystal_clicks(
     mut interactions: Query<
-        (&Interaction, &CrystalSlot, &mut BackgroundColor),
+        (&Interaction, &CrystalSlot),
         Changed<Interaction>,
     >,
     mut selected: ResMut<SelectedCrystals>,
-    buttons: Res<ButtonInput<MouseButton>>,
 ) {
-    for (interaction, slot, mut color) in &mut interactions {
+    for (interaction, slot) in &mut interactions {
         if *interaction == Interaction::Pressed {
             if selected.second == Some(slot.index) {
                 selected.second = None;
             } else if selected.first == Some(slot.index) {
                 selected.first = None;
-            } else if selected.first == None {
+            } else if selected.first.is_none() {
                 selected.first = Some(slot.index);
-            } else if selected.second == None {
+            } else if selected.second.is_none() {
                 selected.second = Some(slot.index);
             } else {
                 selected.first = selected.second;
@@ -499,70 +543,92 @@ fn handle_selected_crystal(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_inventory_shortcuts(
     keys: Res<ButtonInput<KeyCode>>,
     mut selected: ResMut<SelectedCrystals>,
-    mut frames: Query<Entity, With<CrystalFrame>>,
+    frames: Query<Entity, With<CrystalFrame>>,
     mut crystals: Query<(&mut CrystalSlot, &ChildOf, &mut ImageNode)>,
     mut inventory: ResMut<Inventory>,
     sfx: Res<SFX>,
     mut commands: Commands,
     bar_query: Query<&mut XPBar>,
 ) {
-    if keys.just_pressed(KeyCode::KeyX) {
-        if let Some(index) = selected.first {
-            inventory.sell(index, bar_query);
-            commands.spawn(AudioPlayer(sfx.sell.clone()));
-            for (mut crystal, parent, _) in &mut crystals {
-                if Some(crystal.index) == selected.first {
-                    commands
-                        .entity(frames.get(parent.parent()).unwrap())
-                        .despawn();
-                }
+    if keys.just_pressed(KeyCode::KeyX)
+        && let Some(index) = selected.first
+    {
+        inventory.sell(index, bar_query);
+        commands.spawn(AudioPlayer(sfx.sell.clone()));
+        for (mut crystal, parent, _) in &mut crystals {
+            if Some(crystal.index) == selected.first {
+                commands
+                    .entity(frames.get(parent.parent()).unwrap())
+                    .despawn();
+            }
 
-                if crystal.index > selected.first.unwrap() {
-                    crystal.index -= 1;
-                }
+            if crystal.index > selected.first.unwrap() {
+                crystal.index -= 1;
             }
-            selected.first = None;
         }
+        selected.first = None;
     }
 
-    if keys.just_pressed(KeyCode::KeyC) {
-        if let (Some(a), Some(b)) = (selected.first, selected.second) {
-            inventory.combine(a, b);
-            commands.spawn(AudioPlayer(sfx.combine.clone()));
-            for (mut crystal, parent, mut image) in &mut crystals {
-                if Some(crystal.index) == selected.first {
+    if keys.just_pressed(KeyCode::KeyC)
+        && let (Some(a), Some(b)) = (selected.first, selected.second)
+    {
+        inventory.combine(a, b);
+        commands.spawn(AudioPlayer(sfx.combine.clone()));
+        for (mut crystal, parent, mut image) in &mut crystals {
+            if Some(crystal.index) == selected.first {
+                commands
+                    .entity(frames.get(parent.parent()).unwrap())
+                    .despawn();
+            }
+            
+            if Some(crystal.index) == selected.second {
+                image.color = inventory.crystals[crystal.index].color.to_bevy();
+            }
+            
+            if crystal.index > selected.first.unwrap() {
+                crystal.index -= 1;
+            }
+        }
+        selected.first = None;
+        selected.second = None;
+    }
+
+    if keys.just_pressed(KeyCode::KeyQ)
+        && let Some(index) = selected.first
+    {
+        let swapped = inventory.equip(index);
+        commands.spawn(AudioPlayer(sfx.combine.clone()));
+        for (mut crystal, parent, mut image) in &mut crystals {
+            if crystal.index == index {
+                if swapped {
+                    image.color = inventory.crystals[index].color.to_bevy();
+                } else {
                     commands
                         .entity(frames.get(parent.parent()).unwrap())
                         .despawn();
                 }
-                
-                if Some(crystal.index) == selected.second {
-                    image.color = inventory.crystals[crystal.index].color.to_bevy();
-                }
-                
-                if crystal.index > selected.first.unwrap() {
-                    crystal.index -= 1;
-                }
+            } else if !swapped && crystal.index > index {
+                crystal.index -= 1;
             }
-            selected.first = None;
-            selected.second = None;
         }
+        selected.first = None;
     }
 }
 
 fn update_inventory_slots(
     mut frame_query: Query<(&mut Node, &mut Transform, &GlobalTransform), With<CrystalFrame>>,
-    crystal_query: Query<(&CrystalSlot, &ChildOf)>,
+    crystal_query: Query<&ChildOf, With<CrystalSlot>>,
     window: Query<&Window, With<PrimaryWindow>>,
     scroll_offset: Res<ScrollOffsetResource>,
 ) {
     let window = window.single().unwrap();
     let max_distance = window.width() / 2.0;
 
-    for (slot, parent) in crystal_query {
+    for parent in crystal_query {
         let (mut node, mut transform, global) = frame_query.get_mut(parent.parent()).unwrap();
         node.position_type = PositionType::Relative;
         node.left = Val::Px(scroll_offset.0);
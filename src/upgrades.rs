@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use rand::seq::IndexedRandom;
+
+use crate::arrow::Quiver;
+use crate::planets::EffectType;
+use crate::player::Inventory;
+use crate::AppState;
+
+const QUIVER_UPGRADE: u32 = 2;
+const MAX_EXTRA_ARROWS: u32 = 4;
+const STEADY_AIM_FACTOR: f32 = 0.75;
+const CRYSTAL_FOCUS_FACTOR: f32 = 0.8;
+const MAX_HULL: u32 = 3;
+
+/// Sent by the XP bar every time the player reaches a new level.
+#[derive(Event)]
+pub struct LevelUp(pub i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upgrade {
+    BiggerQuiver,
+    ReturningArrows,
+    Multishot,
+    SteadyAim,
+    ReinforcedHull,
+}
+
+/// Permanent bonuses collected by levelling up.
+#[derive(Resource)]
+pub struct Upgrades {
+    pub auto_return: bool,
+    pub extra_arrows: u32,
+    /// Multiplier applied to the aim spread, lower is more accurate.
+    pub focus: f32,
+    /// Reinforced hull level, lowers the speed needed to ram enemies and adds ram damage.
+    pub hull: u32,
+}
+
+impl Default for Upgrades {
+    fn default() -> Self {
+        Upgrades { auto_return: false, extra_arrows: 0, focus: 1., hull: 0 }
+    }
+}
+
+impl Upgrades {
+    fn available(&self) -> Vec<Upgrade> {
+        let mut upgrades = vec![Upgrade::BiggerQuiver, Upgrade::SteadyAim];
+        if !self.auto_return {
+            upgrades.push(Upgrade::ReturningArrows);
+        }
+        if self.extra_arrows < MAX_EXTRA_ARROWS {
+            upgrades.push(Upgrade::Multishot);
+        }
+        if self.hull < MAX_HULL {
+            upgrades.push(Upgrade::ReinforcedHull);
+        }
+        upgrades
+    }
+
+    /// Arrows per shot, counting the equipped `Split` crystal.
+    pub fn arrow_count(&self, inventory: &Inventory) -> u32 {
+        1 + self.extra_arrows + (inventory.equipped_level(EffectType::Split) as u32).div_ceil(2)
+    }
+
+    /// Spread multiplier, counting the equipped `Focus` crystal.
+    pub fn focus(&self, inventory: &Inventory) -> f32 {
+        self.focus * CRYSTAL_FOCUS_FACTOR.powi(inventory.equipped_level(EffectType::Focus))
+    }
+}
+
+pub struct UpgradePlugin;
+
+impl Plugin for UpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<LevelUp>()
+            .add_systems(Update, apply_upgrades.run_if(in_state(AppState::InGame)))
+            .insert_resource(Upgrades::default());
+    }
+}
+
+fn apply_upgrades(
+    mut level_ups: EventReader<LevelUp>,
+    mut upgrades: ResMut<Upgrades>,
+    mut quiver: ResMut<Quiver>,
+) {
+    let mut rng = rand::rng();
+    for level_up in level_ups.read() {
+        let Some(upgrade) = upgrades.available().choose(&mut rng).copied() else {
+            continue;
+        };
+        info!("Level {} reached, applying {:?}.", level_up.0, upgrade);
+        match upgrade {
+            Upgrade::BiggerQuiver => {
+                quiver.capacity += QUIVER_UPGRADE;
+                quiver.current += QUIVER_UPGRADE;
+            }
+            Upgrade::ReturningArrows => upgrades.auto_return = true,
+            Upgrade::Multishot => upgrades.extra_arrows += 1,
+            Upgrade::SteadyAim => upgrades.focus *= STEADY_AIM_FACTOR,
+            Upgrade::ReinforcedHull => upgrades.hull += 1,
+        }
+    }
+}
@@ -5,49 +5,83 @@ use bevy::render::view::RenderLayers;
 use bevy_rapier2d::prelude::*;
 use rand::prelude::*;
 
-use crate::enemy::{Enemy, HealthBar, HealthBarOwner, HP};
-use crate::global::{adjusted_glow, regular_polygon_vertices, CircleCollider};
-use crate::player::Player;
-use crate::{AppState, FirstPass, ENEMY_COLOR, GLOW_FACTOR};
+use crate::enemy::{Enemy, MAX_SIDES, MIN_SIDES};
+use crate::global::adjusted_glow;
+use crate::player::{ColorId, Player};
+use crate::{AppState, FirstPass, GLOW_FACTOR};
 use crate::planets::{Effect, EffectType, Planet};
+use crate::elite::roll_modifiers;
+use crate::nest::{make_nest, Guard, Nest, NEST_CHANCE, NEST_CRYSTAL_BONUS};
+use crate::particles::ParticleHandles;
+use crate::physics::CollisionLayer;
+use crate::spawning::{spawn_telegraph, SpawnTelegraph};
 
+/// Position, unscaled radius and entity of every planet still standing.
 #[derive(Resource, Default)]
-pub struct PlanetData(pub Vec<(Vec2, f32)>);
+pub struct PlanetData(pub Vec<(Vec2, f32, Entity)>);
+
+/// Crystal colors of this run's planets.
+#[derive(Resource, Default)]
+pub struct Palette(pub Vec<ColorId>);
 
 #[derive(Resource)]
 pub struct EnemiesCounter(pub i32);
 
+/// Grows with time spent in game, unlocking and favouring enemies with more sides.
+#[derive(Resource, Default)]
+pub struct Difficulty(pub f32);
+
 const  NUM_COLORS: i32 = 4;
+/// Planet meshes and colliders are drawn at this multiple of their `PlanetData` radius.
+pub const PLANET_SCALE: f32 = 2.0;
+/// Seconds of play per point of difficulty.
+const DIFFICULTY_RAMP: f32 = 60.0;
+/// How widely spawns spread around the most common side count.
+const SPAWN_SPREAD: f32 = 4.0;
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Update, spawn_enemies.run_if(in_state(AppState::InGame)))
+            .add_systems(Update, (spawn_enemies, raise_difficulty).run_if(in_state(AppState::InGame)))
             .add_systems(OnEnter(AppState::InGame), spawn_planets)
             .insert_resource(EnemiesCounter(0))
-            .insert_resource(PlanetData::default());
+            .insert_resource(Difficulty::default())
+            .insert_resource(PlanetData::default())
+            .insert_resource(Palette::default());
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_enemies(
     mut commands: Commands,
-    mut enemies: ResMut<EnemiesCounter>,
+    enemies: Res<EnemiesCounter>,
+    telegraph_query: Query<&SpawnTelegraph>,
+    guard_query: Query<(), With<Guard>>,
+    nest_query: Query<(), With<Nest>>,
     mut cooldown: Local<f32>,
     time: Res<Time>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     player_query: Query<&Transform, With<Player>>,
-    planet_data: Res<PlanetData>
+    planet_data: Res<PlanetData>,
+    difficulty: Res<Difficulty>,
+    particle_handles: Res<ParticleHandles>,
 ) {
+    // enemies still on their way count too, guards stay home so they don't
+    let hatching = telegraph_query
+        .iter()
+        .filter(|telegraph| !telegraph.source().is_some_and(|source| nest_query.contains(source)))
+        .count();
+    let pending = enemies.0 - guard_query.iter().count() as i32 + hatching as i32;
     *cooldown += time.delta_secs();
-    if *cooldown > f32::powf(enemies.0 as f32 / 5.0, 2.0) + 0.1 {
+    if *cooldown > f32::powf(pending as f32 / 5.0, 2.0) + 0.1 {
         *cooldown = 0.0;
         let player = player_query.single().unwrap();
 
         let mut rng = rand::rng();
-        let sides = rng.random_range(3..5);
+        let sides = roll_sides(&mut rng, difficulty.0);
         let mut tries = 0;
         let spawn_pos = loop {
             tries += 1;
@@ -63,43 +97,34 @@ fn spawn_enemies(
         };
 
 
-        let id = commands.spawn((
-            Enemy {
-                sides
-            },
-            Mesh2d(meshes.add(RegularPolygon::new(30.0,sides as u32))),
-            MeshMaterial2d(materials.add(ColorMaterial::from_color(ENEMY_COLOR))),
-            HP {
-                current: 4.,
-                max: 4.
-            },
-            CircleCollider(30.0),
-            Transform::from_translation(spawn_pos.extend(0.)),
-            GlobalTransform::default(),
-            Collider::convex_hull(&regular_polygon_vertices(30., sides as usize).iter().map(|e| {Vect::from(e.clone())}).collect::<Vec<Vect>>()).unwrap(),
-            RigidBody::Dynamic,
-            Velocity::angular(2.0 * PI / 3.0),
-            Restitution::coefficient(1.0),
-            RenderLayers::layer(0),
+        let enemy = Enemy::new(sides);
+        let modifiers = roll_modifiers(&mut rng, &enemy, difficulty.0);
+        spawn_telegraph(&mut commands, &mut meshes, &mut materials, &particle_handles, enemy, modifiers, spawn_pos, None);
+    }
+}
 
-            FirstPass,
-        )).id();
+/// Side count of a new enemy, weighted by `difficulty`.
+pub fn roll_sides(rng: &mut impl Rng, difficulty: f32) -> i32 {
+    (MIN_SIDES..=MAX_SIDES)
+        .collect::<Vec<_>>()
+        .choose_weighted(rng, |sides| side_weight(*sides, difficulty))
+        .map_or(MIN_SIDES, |sides| *sides)
+}
 
-        commands.spawn((
-            Mesh2d(meshes.add(Rectangle::new(75.0, 15.0))),
-            MeshMaterial2d(materials.add(ColorMaterial::from_color(Color::linear_rgb(GLOW_FACTOR, 0.0, 0.0)))),
-            Transform {
-                translation: (spawn_pos + Vec2::new(0., 40.)).extend(0.1),
-                ..default()
-            },
-            GlobalTransform::default(),
-            HealthBar,
-            HealthBarOwner(id),
-            RenderLayers::layer(0),
-            FirstPass,
-        ));
-        enemies.0 += 1;
+/// Relative spawn chance of an enemy with `sides` sides at `difficulty`.
+/// Every point of difficulty unlocks two more sides and shifts the most common shape by one.
+fn side_weight(sides: i32, difficulty: f32) -> f32 {
+    let extra = (sides - MIN_SIDES) as f32;
+    if extra > 1. + difficulty * 2. {
+        return 0.;
     }
+    // past the biggest shape the peak stays on it, or every weight underflows to 0
+    let peak = difficulty.min((MAX_SIDES - MIN_SIDES) as f32);
+    (-(extra - peak).powi(2) / SPAWN_SPREAD).exp()
+}
+
+fn raise_difficulty(mut difficulty: ResMut<Difficulty>, time: Res<Time>) {
+    difficulty.0 += time.delta_secs() / DIFFICULTY_RAMP;
 }
 
 fn spawn_planets(
@@ -107,6 +132,7 @@ fn spawn_planets(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut planet_data: ResMut<PlanetData>,
+    mut palette: ResMut<Palette>,
 ) {
     info!("Spawning planets.");
     let mut rng = rand::rng();
@@ -115,6 +141,7 @@ fn spawn_planets(
     for _i in 0..NUM_COLORS {
         colours.push(Color::srgb(rng.random::<f32>() , rng.random::<f32>() , rng.random::<f32>()));
     }
+    palette.0 = colours.iter().map(|colour| ColorId::from_bevy(&adjusted_glow(*colour, GLOW_FACTOR), true).unwrap()).collect();
     'outer: for _ in 0..30 {
 
         let pos_x =  loop { let x = rng.random_range(-1500.0..1500.0); if (x as f32).abs() > 200. {break x} };
@@ -169,25 +196,26 @@ fn spawn_planets(
         mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
         mesh.insert_indices(bevy::render::mesh::Indices::U32(indices));
 
-        planet_data.0.push((Vec2::new(pos_x, pos_y), max_radius));
 
         let collider_vertices: Vec<Vec2> = points.iter().map(|f| Vec2::new(f[0], f[1])).collect();
         let collider_indices: Vec<[u32; 2]> = (0..sides as u32)
             .map(|i| [i, (i + 1) % sides as u32])
             .collect();
         let color = adjusted_glow(colours[rng.random_range(0..NUM_COLORS as usize)], GLOW_FACTOR);
+        let nested = rng.random_bool(NEST_CHANCE);
 
-        commands.spawn( (
+        let id = commands.spawn( (
             Planet {
                 color,
-                effect: Effect { effect_type: EffectType::Poison, level: 2 },
+                effect: Effect { effect_type: EffectType::Poison, level: 2 + if nested { NEST_CRYSTAL_BONUS } else { 0 } },
                 hp: rng.random_range(1.0..12.0),
             },
             Mesh2d(meshes.add(mesh)),
             MeshMaterial2d(materials.add(ColorMaterial::from_color(color))),
-            Transform::from_translation(Vec3::new(pos_x, pos_y, 0.)).with_scale(Vec3::splat(2.)),
+            Transform::from_translation(Vec3::new(pos_x, pos_y, 0.)).with_scale(Vec3::splat(PLANET_SCALE)),
             RigidBody::Dynamic,
             Collider::polyline(collider_vertices, Some(collider_indices)),
+            CollisionLayer::Planet.bundle(),
             Restitution::coefficient(1.0),
             ActiveEvents::COLLISION_EVENTS,
 
@@ -195,18 +223,60 @@ fn spawn_planets(
             FirstPass,
 
 
-        ));
+        )).id();
+        planet_data.0.push((Vec2::new(pos_x, pos_y), max_radius, id));
+        if nested {
+            make_nest(&mut commands, &mut meshes, &mut materials, id, max_radius);
+        }
         z += 1;
 
     }
     info!("Spawned {} planets.", z);
 }
 
-fn is_position_safe(pos: Vec2, planets: &PlanetData) -> bool {
-    for (planet_pos, planet_radius) in &planets.0 {
-        if pos.distance(*planet_pos) < *planet_radius + 30.0 {
+pub fn is_position_safe(pos: Vec2, planets: &PlanetData) -> bool {
+    for (planet_pos, planet_radius, _) in &planets.0 {
+        if pos.distance(*planet_pos) < *planet_radius * PLANET_SCALE + 30.0 {
             return false;
         }
     }
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_small_shapes_at_start() {
+        assert!(side_weight(MIN_SIDES, 0.) > 0.);
+        assert!(side_weight(MIN_SIDES + 1, 0.) > 0.);
+        assert_eq!(side_weight(MIN_SIDES + 2, 0.), 0.);
+    }
+
+    #[test]
+    fn biggest_shape_is_most_common_late() {
+        let weights: Vec<f32> = (MIN_SIDES..=MAX_SIDES).map(|sides| side_weight(sides, 1000.)).collect();
+        assert!(weights.iter().all(|weight| weight.is_finite()));
+        assert_eq!(*weights.last().unwrap(), 1.);
+    }
+
+    #[test]
+    fn positions_inside_scaled_planets_are_unsafe() {
+        let planets = PlanetData(vec![(Vec2::ZERO, 100., Entity::from_raw(1))]);
+        assert!(!is_position_safe(Vec2::new(150., 0.), &planets));
+        assert!(!is_position_safe(Vec2::new(220., 0.), &planets));
+        assert!(is_position_safe(Vec2::new(240., 0.), &planets));
+    }
+
+    #[test]
+    fn rolls_valid_sides_at_any_difficulty() {
+        let mut rng = rand::rng();
+        for difficulty in [0., 1.5, 9., 29.5, 60., 1e6] {
+            for _ in 0..50 {
+                let sides = roll_sides(&mut rng, difficulty);
+                assert!((MIN_SIDES..=MAX_SIDES).contains(&sides), "{sides} sides at difficulty {difficulty}");
+            }
+        }
+    }
+}
@@ -1,12 +1,15 @@
 use bevy::prelude::*;
 use bevy::render::view::RenderLayers;
 use bevy_hanabi::ParticleEffect;
+use bevy_rapier2d::prelude::*;
 use rand::prelude::*;
 
-use crate::{global::CircleCollider, particles::ParticleHandles, player::Player, ui::XPBar, AppState, GLOW_FACTOR};
+use crate::{particles::ParticleHandles, physics::{started_collision, CollisionLayer}, player::Player, ui::XPBar, AppState, GLOW_FACTOR};
 use crate::sfx::SFX;
 
 const G: f32 =50000.0;
+/// Sensor radius, bigger than the orb so fast orbs don't skip past the player.
+const ORB_PICKUP_RADIUS: f32 = 8.0;
 
 #[derive(Component)]
 pub struct XPOrb(pub f32);
@@ -40,19 +43,23 @@ fn move_xp_orb(
 }
 
 fn xp_orb_collision(
-    player_query: Query<(&Transform, &CircleCollider), With<Player>>,
-    orb_query: Query<(&Transform, Entity, &XPOrb)>,
+    mut collision_events: EventReader<CollisionEvent>,
+    layer_query: Query<&CollisionLayer>,
+    orb_query: Query<&XPOrb>,
     mut xp_bar_query: Query<&mut XPBar>,
     mut commands: Commands,
     sfx: Res<SFX>,
 ) {
-    let (tr, collider) = player_query.single().unwrap();
     let mut xp_bar = xp_bar_query.single_mut().unwrap();
 
-    for (orb_tr, ent, orb) in orb_query {
-        if orb_tr.translation.truncate().distance(tr.translation.truncate()) < collider.0 {
+    for event in collision_events.read() {
+        // orbs only ever touch the player
+        let Some((ent, _, _)) = started_collision(event, &layer_query, CollisionLayer::Orb) else {
+            continue;
+        };
+        if let Ok(orb) = orb_query.get(ent) {
             commands.entity(ent).despawn();
-            
+
             xp_bar.current += orb.0;
             commands.spawn(AudioPlayer(sfx.xp.clone()));
         }
@@ -69,10 +76,15 @@ pub fn spawn_orbs(
 ) {
     let mut rng = rand::rng();
     for size in losowe_sumujace_sie_do_x(4, sum) {
+        let radius = rng.random_range(0.5..2.3);
         commands.spawn((
             XPOrb(size as f32),
             Transform::from_translation(translation + Vec3::new(rng.random_range(-30.0..30.0), rng.random_range(-30.0..30.0), 0.)),
-            Mesh2d(meshes.add(Circle::new(rng.random_range(0.5..2.3)))),
+            Collider::ball(ORB_PICKUP_RADIUS),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            CollisionLayer::Orb.bundle(),
+            Mesh2d(meshes.add(Circle::new(radius))),
             MeshMaterial2d(materials.add(ColorMaterial::from_color(Color::linear_rgb(GLOW_FACTOR, GLOW_FACTOR, 0.)))),
             ParticleEffect::new(particle_handles.xp_trail.clone()),
             RenderLayers::layer(0)
@@ -91,8 +103,8 @@ fn losowe_sumujace_sie_do_x(n: usize, x: f64) -> Vec<f64> {
     let suma: f64 = liczby.iter().sum();
 
     // Przeskaluj, żeby suma wynosiła dokładnie x
-    for i in 0..n {
-        liczby[i] = liczby[i] / suma * x;
+    for liczba in liczby.iter_mut() {
+        *liczba = *liczba / suma * x;
     }
 
     liczby